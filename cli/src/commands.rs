@@ -0,0 +1,257 @@
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+use amt::ec_algebra::{CanonicalDeserialize, CanonicalSerialize};
+use anyhow::{anyhow, bail, Context, Result};
+use recovery::recover_from_lines;
+use zg_encoder::{
+    commitment_from_bytes, commitment_to_bytes, compute_file_root,
+    constants::{G1Curve, BLOB_COL_N, BLOB_ROW_ENCODED},
+    CommitmentFormat, EncodedBlob, EncodedSlice, LightEncodedSlice, RawData,
+    ZgEncoderParams, ZgSignerParams,
+};
+
+/// Files written by `encode` next to the slice files.
+pub const COMMITMENT_FILE: &str = "erasure_commitment";
+pub const ROOT_FILE: &str = "storage_root";
+
+pub fn slice_file_name(index: usize) -> String {
+    format!("row_{:03}.slice", index)
+}
+
+/// Writes the hex commitment and root, and every row as a serialized
+/// `EncodedSlice`, into `out_dir`.
+pub fn encode(
+    params: &ZgEncoderParams, input: &[u8], out_dir: &Path,
+    format: CommitmentFormat,
+) -> Result<(Vec<u8>, [u8; 32])> {
+    let raw_data = RawData::try_from(input)
+        .map_err(|e| anyhow!("Cannot encode input: {}", e))?;
+    let encoded_blob = EncodedBlob::build(&raw_data.into(), params);
+    let commitment =
+        commitment_to_bytes(&encoded_blob.get_affine_commitment(), format);
+    let root = encoded_blob.get_file_root();
+
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("Cannot create {}", out_dir.display()))?;
+    let write = |name: &str, contents: &[u8]| {
+        let path = out_dir.join(name);
+        fs::write(&path, contents)
+            .with_context(|| format!("Cannot write {}", path.display()))
+    };
+    write(
+        COMMITMENT_FILE,
+        format!("{}\n", hex::encode(&commitment)).as_bytes(),
+    )?;
+    write(ROOT_FILE, format!("{}\n", hex::encode(root)).as_bytes())?;
+    for index in 0..BLOB_ROW_ENCODED {
+        let mut raw = vec![];
+        encoded_blob
+            .get_row(index)
+            .serialize_uncompressed(&mut raw)?;
+        write(&slice_file_name(index), &raw)?;
+    }
+    Ok((commitment, root))
+}
+
+pub fn parse_commitment(
+    value: &str, format: CommitmentFormat,
+) -> Result<G1Curve> {
+    let raw = parse_hex(value).context("Malformed commitment")?;
+    let commitment = commitment_from_bytes(&raw, format)
+        .map_err(|e| anyhow!("Malformed commitment: {}", e))?;
+    Ok(commitment.into())
+}
+
+pub fn parse_root(value: &str) -> Result<[u8; 32]> {
+    parse_hex(value)
+        .context("Malformed storage root")?
+        .try_into()
+        .map_err(|_| anyhow!("Storage root must be 32 bytes"))
+}
+
+/// Verifies each slice file, returning the row index of the valid ones.
+pub fn verify(
+    params: &ZgSignerParams, commitment: &G1Curve, root: &[u8; 32],
+    files: &[impl AsRef<Path>],
+) -> Vec<Result<usize>> {
+    files
+        .iter()
+        .map(|file| {
+            let slice = read_slice(file.as_ref())?;
+            slice.verify(params, commitment, root, None)?;
+            Ok(slice.index)
+        })
+        .collect()
+}
+
+/// Recovers the original file from the slice files of `dir`. Files that do not
+/// hold a well-formed row are skipped with a warning.
+pub fn recover(dir: &Path) -> Result<Vec<u8>> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("Cannot read {}", dir.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<_>>()?;
+    entries.sort();
+
+    let mut lines = BTreeMap::new();
+    for path in entries.iter().filter(|path| path.is_file()) {
+        let slice = match read_slice(path) {
+            Ok(slice) => slice,
+            Err(e) => {
+                warn!("Skipping {}: {:#}", path.display(), e);
+                continue;
+            }
+        };
+        let line = slice.amt_row();
+        if slice.index >= BLOB_ROW_ENCODED || line.len() != BLOB_COL_N {
+            warn!("Skipping {}: malformed row {}", path.display(), slice.index);
+            continue;
+        }
+        if lines.insert(slice.index, line).is_some() {
+            warn!("Row {} appears more than once", slice.index);
+        }
+    }
+    info!(rows = lines.len(), "Recovering");
+    recover_from_lines(&lines).map_err(|e| anyhow!(e))
+}
+
+/// Describes a serialized `EncodedSlice` or `LightEncodedSlice`.
+pub fn inspect(raw: &[u8], format: CommitmentFormat) -> Result<String> {
+    let (kind, row_len, light) = if let Some(slice) =
+        deserialize_exact::<EncodedSlice>(raw)
+    {
+        let (amt_index, merkle_index) =
+            (slice.amt().index, slice.merkle().index());
+        if amt_index != slice.index || merkle_index != slice.index {
+            bail!(
+                "EncodedSlice with inconsistent indices: slice {}, amt {}, \
+                 merkle {}",
+                slice.index,
+                amt_index,
+                merkle_index
+            );
+        }
+        let row_len = slice.amt().row.row.len();
+        ("EncodedSlice", Some(row_len), slice.into_light_slice())
+    } else if let Some(light) = deserialize_exact::<LightEncodedSlice>(raw) {
+        ("LightEncodedSlice", None, light)
+    } else {
+        bail!("Neither an EncodedSlice nor a LightEncodedSlice");
+    };
+
+    let mut out = String::new();
+    let mut line = |key: &str, value: String| {
+        writeln!(out, "{:<20} {}", format!("{}:", key), value).unwrap()
+    };
+    line("type", kind.to_string());
+    line("index", light.index.to_string());
+    if let Some(row_len) = row_len {
+        line("row", format!("{} scalars", row_len));
+    }
+    line(
+        "erasure_commitment",
+        hex::encode(commitment_to_bytes(&light.amt_commitment, format)),
+    );
+    line(
+        "storage_root",
+        hex::encode(compute_file_root(&light.merkle_root)),
+    );
+    line("amt_proof", format!("{} levels", light.amt_proof.len()));
+    line(
+        "amt_high_commitment",
+        hex::encode(commitment_to_bytes(&light.amt_high_commitment, format)),
+    );
+    for (coset, root) in light.merkle_root.iter().enumerate() {
+        line(&format!("merkle_root[{}]", coset), hex::encode(root));
+    }
+    line(
+        "merkle_proof",
+        format!("{} nodes", light.merkle_proof.len()),
+    );
+    line("merkle_leaf", hex::encode(light.merkle_leaf));
+    Ok(out)
+}
+
+fn read_slice(path: &Path) -> Result<EncodedSlice> {
+    let raw = fs::read(path)
+        .with_context(|| format!("Cannot read {}", path.display()))?;
+    deserialize_exact(&raw).ok_or_else(|| anyhow!("Not an EncodedSlice"))
+}
+
+/// Deserializes `T` when it spans the whole input.
+fn deserialize_exact<T: CanonicalDeserialize>(mut raw: &[u8]) -> Option<T> {
+    let value = T::deserialize_uncompressed(&mut raw).ok()?;
+    raw.is_empty().then_some(value)
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>> {
+    let value = value.trim();
+    Ok(hex::decode(value.strip_prefix("0x").unwrap_or(value))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use zg_encoder::{
+        constants::BLOB_ROW_N, CommitmentFormat, ZgEncoderParams,
+        ZgSignerParams,
+    };
+
+    use super::{
+        encode, inspect, parse_commitment, parse_root, recover,
+        slice_file_name, verify, COMMITMENT_FILE, ROOT_FILE,
+    };
+
+    const PARAM_DIR: &str = "../crates/amt/pp";
+    #[test]
+    fn test_round_trip() {
+        let encoder_params = ZgEncoderParams::from_dir_mont(
+            PARAM_DIR,
+            true,
+            None,
+            &Default::default(),
+        )
+        .unwrap();
+        let signer_params =
+            ZgSignerParams::from_dir_mont(PARAM_DIR, &Default::default())
+                .unwrap();
+        let dir = std::env::temp_dir()
+            .join(format!("zg-encoder-cli-{}", std::process::id()));
+        let mut data = vec![0u8; 1000];
+        StdRng::seed_from_u64(19).fill(&mut data[..]);
+
+        let format = CommitmentFormat::EvmBigEndian;
+        encode(&encoder_params, &data, &dir, format).unwrap();
+        let read = |name| fs::read_to_string(dir.join(name)).unwrap();
+        let commitment =
+            parse_commitment(&read(COMMITMENT_FILE), format).unwrap();
+        let root = parse_root(&read(ROOT_FILE)).unwrap();
+
+        let files =
+            [dir.join(slice_file_name(0)), dir.join(slice_file_name(5))];
+        let results = verify(&signer_params, &commitment, &root, &files);
+        assert_eq!(
+            results.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+            [0, 5]
+        );
+        // A wrong root fails every slice
+        let results = verify(&signer_params, &commitment, &[0; 32], &files);
+        assert!(results.iter().all(Result::is_err));
+
+        let description =
+            inspect(&fs::read(&files[1]).unwrap(), format).unwrap();
+        assert!(description.contains("EncodedSlice"), "{description}");
+        assert!(description.contains(&hex::encode(root)), "{description}");
+
+        // Recover without the rows of the second coset
+        for index in BLOB_ROW_N..2 * BLOB_ROW_N {
+            fs::remove_file(dir.join(slice_file_name(index))).unwrap();
+        }
+        let recovered = recover(&dir).unwrap();
+        assert_eq!(recovered, data);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
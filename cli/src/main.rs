@@ -0,0 +1,200 @@
+#[macro_use]
+extern crate tracing;
+
+mod commands;
+
+use std::{fs, path::PathBuf};
+
+use amt::ParamsCheck;
+use anyhow::{bail, Context, Result};
+use clap::ArgMatches;
+use tracing::Level;
+use zg_encoder::{
+    constants::{BLOB_COL_LOG, BLOB_ROW_LOG, COSET_N},
+    CommitmentFormat, SelfTestAnswer, ZgEncoderParams, ZgSignerParams,
+};
+
+mod cli {
+    use clap::{arg, command, Arg, Command};
+
+    fn params_arg<'a>() -> Arg<'a> {
+        arg!(-p --params <DIR> "Directory of the AMT params")
+    }
+
+    fn format_arg<'a>() -> Arg<'a> {
+        arg!(--"commitment-format" <FORMAT> "Byte layout of the erasure commitment")
+            .required(false)
+            .possible_values(["ark-uncompressed", "ark-compressed", "evm-big-endian"])
+            .default_value("ark-uncompressed")
+    }
+
+    pub fn cli_app<'a>() -> Command<'a> {
+        command!()
+            .subcommand_required(true)
+            .arg_required_else_help(true)
+            .subcommand(
+                Command::new("encode")
+                    .about("Writes the erasure commitment, the storage root and a slice file per row")
+                    .arg(params_arg())
+                    .arg(format_arg())
+                    .arg(arg!(<INPUT> "File to encode"))
+                    .arg(arg!(<OUT_DIR> "Directory of the output files")),
+            )
+            .subcommand(
+                Command::new("verify")
+                    .about("Checks slice files against an erasure commitment and a storage root")
+                    .arg(params_arg())
+                    .arg(format_arg())
+                    .arg(arg!(--commitment <HEX> "Erasure commitment"))
+                    .arg(arg!(--root <HEX> "Storage root"))
+                    .arg(arg!(<SLICES>... "Serialized EncodedSlice files")),
+            )
+            .subcommand(
+                Command::new("recover")
+                    .about("Rebuilds the original file from a directory of slice files")
+                    .arg(arg!(<ROWS_DIR> "Directory of serialized EncodedSlice files"))
+                    .arg(arg!(<OUTPUT> "Recovered file")),
+            )
+            .subcommand(
+                Command::new("self-test")
+                    .about("Encodes the built-in self-test payload and checks it against the known answer")
+                    .arg(params_arg()),
+            )
+            .subcommand(
+                Command::new("inspect")
+                    .about("Prints a serialized EncodedSlice or LightEncodedSlice")
+                    .arg(format_arg())
+                    .arg(arg!(<FILE> "Slice file")),
+            )
+    }
+}
+
+fn main() -> Result<()> {
+    let matches = cli::cli_app().get_matches();
+
+    tracing_subscriber::fmt()
+        .with_max_level(Level::INFO)
+        .with_target(false)
+        .with_writer(std::io::stderr)
+        .init();
+
+    match matches.subcommand() {
+        Some(("encode", args)) => {
+            let params = ZgEncoderParams::from_dir_mont(
+                arg(args, "params"),
+                false,
+                None,
+                &ParamsCheck::Enforce.into(),
+            )?;
+            let input = fs::read(arg(args, "INPUT")).with_context(|| {
+                format!("Cannot read {}", arg(args, "INPUT"))
+            })?;
+            let (commitment, root) = commands::encode(
+                &params,
+                &input,
+                arg(args, "OUT_DIR").as_ref(),
+                commitment_format(args),
+            )?;
+            println!("erasure_commitment: {}", hex::encode(commitment));
+            println!("storage_root: {}", hex::encode(root));
+        }
+        Some(("verify", args)) => {
+            let format = commitment_format(args);
+            let commitment =
+                commands::parse_commitment(arg(args, "commitment"), format)?;
+            let root = commands::parse_root(arg(args, "root"))?;
+            let params = ZgSignerParams::from_dir_mont(
+                arg(args, "params"),
+                &ParamsCheck::Enforce.into(),
+            )?;
+            let files: Vec<PathBuf> = args
+                .values_of("SLICES")
+                .unwrap_or_default()
+                .map(PathBuf::from)
+                .collect();
+
+            let mut failed = 0;
+            for (file, result) in files.iter().zip(commands::verify(
+                &params,
+                &commitment,
+                &root,
+                &files,
+            )) {
+                match result {
+                    Ok(index) => {
+                        println!("{}: row {} ok", file.display(), index)
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        println!("{}: {:#}", file.display(), e);
+                    }
+                }
+            }
+            if failed > 0 {
+                bail!(
+                    "{} of {} slices failed verification",
+                    failed,
+                    files.len()
+                );
+            }
+        }
+        Some(("recover", args)) => {
+            let data = commands::recover(arg(args, "ROWS_DIR").as_ref())?;
+            fs::write(arg(args, "OUTPUT"), &data).with_context(|| {
+                format!("Cannot write {}", arg(args, "OUTPUT"))
+            })?;
+            info!(bytes = data.len(), "Recovered");
+        }
+        Some(("inspect", args)) => {
+            let raw = fs::read(arg(args, "FILE")).with_context(|| {
+                format!("Cannot read {}", arg(args, "FILE"))
+            })?;
+            print!("{}", commands::inspect(&raw, commitment_format(args))?);
+        }
+        Some(("self-test", args)) => {
+            let params = ZgEncoderParams::from_dir_mont(
+                arg(args, "params"),
+                false,
+                None,
+                &ParamsCheck::Enforce.into(),
+            )?;
+            let verifier = ZgSignerParams::from_dir_mont(
+                arg(args, "params"),
+                &ParamsCheck::Enforce.into(),
+            )?;
+            let expected = SelfTestAnswer::known();
+            let answer = zg_encoder::self_test(
+                &params,
+                Some(&verifier),
+                expected.as_ref(),
+            )?;
+            println!(
+                "geometry: ({}, {}, {})",
+                BLOB_ROW_LOG, BLOB_COL_LOG, COSET_N
+            );
+            println!(
+                "erasure_commitment: {}",
+                hex::encode(&answer.erasure_commitment)
+            );
+            println!("storage_root: {}", hex::encode(answer.storage_root));
+            match expected {
+                Some(_) => println!("known answer: matches"),
+                None => println!("known answer: none recorded"),
+            }
+        }
+        _ => unreachable!("a subcommand is required"),
+    }
+    Ok(())
+}
+
+fn arg<'a>(args: &'a ArgMatches, name: &str) -> &'a str {
+    args.value_of(name).expect("required by the parser")
+}
+
+fn commitment_format(args: &ArgMatches) -> CommitmentFormat {
+    match arg(args, "commitment-format") {
+        "ark-compressed" => CommitmentFormat::ArkCompressed,
+        "evm-big-endian" => CommitmentFormat::EvmBigEndian,
+        _ => CommitmentFormat::ArkUncompressed,
+    }
+}
@@ -1,4 +1,4 @@
-use amt::{AMTParams, AMTVerifyParams, PowerTau};
+use amt::{AMTParams, AMTVerifyParams, ParamsCheck, ParamsChecker, PowerTau};
 use anyhow::{bail, Result};
 use tracing::Level;
 
@@ -21,7 +21,7 @@ fn parse_param() -> Result<(usize, usize, usize, Option<String>)> {
     Ok((args[1].parse()?, args[2].parse()?, args[3].parse()?, path))
 }
 
-fn main() {
+fn main() -> Result<()> {
     let (depth, verify_depth, coset, ptau_dir) = match parse_param() {
         Ok(x) => x,
         Err(e) => {
@@ -38,6 +38,7 @@ fn main() {
     let create_mode = ptau_dir.is_none();
     let dir = ptau_dir.unwrap_or("./params/test".into());
     let pp = PowerTau::from_dir(&dir, depth, create_mode);
+    let check = ParamsChecker::new(ParamsCheck::Enforce);
 
     for coset_index in 0..coset {
         AMTParams::from_dir_mont(
@@ -47,7 +48,15 @@ fn main() {
             coset_index,
             true,
             Some(&pp),
-        );
-        AMTVerifyParams::from_dir_mont(&dir, depth, verify_depth, coset_index);
+            &check,
+        )?;
+        AMTVerifyParams::from_dir_mont(
+            &dir,
+            depth,
+            verify_depth,
+            coset_index,
+            &check,
+        )?;
     }
+    Ok(())
 }
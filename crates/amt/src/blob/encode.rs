@@ -1,5 +1,5 @@
 use ark_std::cfg_chunks_mut;
-use std::path::Path;
+use std::{convert::Infallible, path::Path};
 
 #[cfg(not(feature = "cuda-bls12-381"))]
 use ark_bn254::Bn254;
@@ -15,7 +15,7 @@ use crate::{
     proofs::{AllProofs, AmtProofError, Proof},
     prove_params::AMTProofs,
     utils::{bitreverse, change_matrix_direction, index_reverse},
-    AMTParams, AMTVerifyParams, PowerTau,
+    AMTParams, AMTVerifyParams, ParamsChecker, ParamsIntegrityError, PowerTau,
 };
 
 pub struct EncoderParams<
@@ -57,11 +57,21 @@ where AMTParams<PE>: AMTProofs<PE = PE>
     }
 
     pub fn from_builder<F: Fn(usize) -> AMTParams<PE>>(f: F) -> Self {
+        match Self::try_from_builder(|coset| Ok::<_, Infallible>(f(coset))) {
+            Ok(params) => params,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Like `from_builder`, stopping at the first coset `f` fails on.
+    pub fn try_from_builder<E, F: Fn(usize) -> Result<AMTParams<PE>, E>>(
+        f: F,
+    ) -> Result<Self, E> {
         Self::assert_validity();
 
         let mut amt_list = vec![];
         for coset in 0..COSET_N {
-            let amt = f(coset);
+            let amt = f(coset)?;
             amt_list.push(amt);
         }
 
@@ -70,7 +80,7 @@ where AMTParams<PE>: AMTProofs<PE = PE>
             Err(_) => unreachable!(),
         };
 
-        Self { amt_list }
+        Ok(Self { amt_list })
     }
 
     const fn assert_validity() {
@@ -91,6 +101,14 @@ where AMTParams<PE>: AMTProofs<PE = PE>
     pub fn process_blob(
         &self, raw_blob: &[Fr<PE>],
     ) -> [HalfBlob<PE, LOG_COL, LOG_ROW>; COSET_N] {
+        self.process_blob_cancellable(raw_blob, || false).unwrap()
+    }
+
+    /// Like `process_blob`, but calls `cancelled` before each coset FFT and
+    /// each `HalfBlob::generate`, and gives up with `None` once it is true.
+    pub fn process_blob_cancellable(
+        &self, raw_blob: &[Fr<PE>], cancelled: impl Fn() -> bool,
+    ) -> Option<[HalfBlob<PE, LOG_COL, LOG_ROW>; COSET_N]> {
         assert_eq!(Self::len(), raw_blob.len());
 
         let mut points = raw_blob.to_vec();
@@ -98,13 +116,19 @@ where AMTParams<PE>: AMTProofs<PE = PE>
 
         let mut blobs = vec![];
         for (idx, amt) in self.amt_list.iter().enumerate() {
+            if cancelled() {
+                return None;
+            }
+            let coset_blob = to_coset_blob::<PE>(&points, idx);
+            if cancelled() {
+                return None;
+            }
             blobs.push(HalfBlob::<PE, LOG_COL, LOG_ROW>::generate(
-                to_coset_blob::<PE>(&points, idx),
-                amt,
+                coset_blob, amt,
             ))
         }
 
-        blobs.try_into().unwrap()
+        Some(blobs.try_into().unwrap())
     }
 }
 
@@ -115,11 +139,11 @@ impl<const COSET_N: usize, const LOG_COL: usize, const LOG_ROW: usize>
     #[instrument(skip_all, level = 3)]
     pub fn from_dir_mont(
         dir: impl AsRef<Path> + Clone, create_mode: bool,
-        pp: Option<&PowerTau<Bn254>>,
-    ) -> Self {
+        pp: Option<&PowerTau<Bn254>>, check: &ParamsChecker,
+    ) -> Result<Self, ParamsIntegrityError> {
         info!("Load AMT params");
 
-        Self::from_builder(|coset| {
+        Self::try_from_builder(|coset| {
             AMTParams::from_dir_mont(
                 dir.clone(),
                 LOG_COL + LOG_ROW,
@@ -127,6 +151,7 @@ impl<const COSET_N: usize, const LOG_COL: usize, const LOG_ROW: usize>
                 coset,
                 create_mode,
                 pp,
+                check,
             )
         })
     }
@@ -268,7 +293,13 @@ mod tests {
 
     static ENCODER: Lazy<TestEncoderContext> = Lazy::new(|| {
         #[cfg(not(feature = "cuda-bls12-381"))]
-        return TestEncoderContext::from_dir_mont("./pp", true, Some(&*PP));
+        return TestEncoderContext::from_dir_mont(
+            "./pp",
+            true,
+            Some(&*PP),
+            &Default::default(),
+        )
+        .unwrap();
         #[cfg(feature = "cuda-bls12-381")]
         return TestEncoderContext::from_dir("./pp", true, Some(&*PP));
     });
@@ -278,7 +309,8 @@ mod tests {
         // Guarantee encoder has complete before loading verifier
         Lazy::force(&ENCODER);
         #[cfg(not(feature = "cuda-bls12-381"))]
-        return TestVerifierContext::from_dir_mont("./pp");
+        return TestVerifierContext::from_dir_mont("./pp", &Default::default())
+            .unwrap();
         #[cfg(feature = "cuda-bls12-381")]
         return TestVerifierContext::from_dir("./pp");
     });
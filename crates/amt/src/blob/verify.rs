@@ -1,9 +1,12 @@
-use std::path::Path;
+use std::{convert::Infallible, path::Path};
 
 #[cfg(not(feature = "cuda-bls12-381"))]
 use ark_bn254::Bn254;
 
-use crate::{ec_algebra::Pairing, verify_params::AMTVerifyParams};
+use crate::{
+    ec_algebra::Pairing, verify_params::AMTVerifyParams, ParamsChecker,
+    ParamsIntegrityError,
+};
 
 pub struct VerifierParams<
     PE: Pairing,
@@ -26,9 +29,18 @@ impl<
     }
 
     fn from_builder<F: Fn(usize) -> AMTVerifyParams<PE>>(f: F) -> Self {
+        match Self::try_from_builder(|coset| Ok::<_, Infallible>(f(coset))) {
+            Ok(params) => params,
+            Err(e) => match e {},
+        }
+    }
+
+    fn try_from_builder<E, F: Fn(usize) -> Result<AMTVerifyParams<PE>, E>>(
+        f: F,
+    ) -> Result<Self, E> {
         let mut amt_list = vec![];
         for coset in 0..COSET_N {
-            let amt = f(coset);
+            let amt = f(coset)?;
             amt_list.push(amt);
         }
 
@@ -37,7 +49,7 @@ impl<
             Err(_) => unreachable!(),
         };
 
-        Self { amt_list }
+        Ok(Self { amt_list })
     }
 
     pub fn from_dir(dir: impl AsRef<Path> + Clone) -> Self {
@@ -56,13 +68,16 @@ impl<
 impl<const COSET_N: usize, const LOG_COL: usize, const LOG_ROW: usize>
     VerifierParams<Bn254, COSET_N, LOG_COL, LOG_ROW>
 {
-    pub fn from_dir_mont(dir: impl AsRef<Path> + Clone) -> Self {
-        Self::from_builder(|coset| {
+    pub fn from_dir_mont(
+        dir: impl AsRef<Path> + Clone, check: &ParamsChecker,
+    ) -> Result<Self, ParamsIntegrityError> {
+        Self::try_from_builder(|coset| {
             AMTVerifyParams::from_dir_mont(
                 dir.clone(),
                 LOG_COL + LOG_ROW,
                 LOG_ROW,
                 coset,
+                check,
             )
         })
     }
@@ -5,6 +5,7 @@ mod blob;
 mod deferred_verification;
 pub mod ec_algebra;
 mod error;
+mod manifest;
 mod power_tau;
 mod proofs;
 mod prove_params;
@@ -16,6 +17,10 @@ pub use blob::{
     verify::VerifierParams,
 };
 pub use deferred_verification::DeferredVerifier;
+pub use manifest::{
+    ManifestEntry, ParamsCheck, ParamsChecker, ParamsIntegrityError,
+    ParamsManifest,
+};
 pub use power_tau::PowerTau;
 pub use proofs::{AmtProofError, Proof};
 pub use prove_params::AMTParams;
@@ -0,0 +1,334 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+
+/// `<file name> <size> <sha256>` of each published params file.
+const PARAMS_MANIFEST: &str = include_str!("../params.manifest");
+
+static MANIFEST: Lazy<ParamsManifest> = Lazy::new(|| {
+    ParamsManifest::parse(PARAMS_MANIFEST)
+        .expect("the built-in params manifest is well-formed")
+});
+
+/// What loading a params file that does not match the manifest does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParamsCheck {
+    /// Fail with `ParamsIntegrityError` before deserializing the file.
+    #[default]
+    Enforce,
+    /// Log a warning and load the file anyway.
+    Warn,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub size: u64,
+    pub sha256: [u8; 32],
+}
+
+#[derive(Debug, Default)]
+pub struct ParamsManifest {
+    entries: HashMap<String, ManifestEntry>,
+}
+
+#[derive(Debug)]
+pub enum ParamsIntegrityError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    IncorrectSize {
+        path: PathBuf,
+        actual: u64,
+        expected: u64,
+    },
+    IncorrectDigest {
+        path: PathBuf,
+        actual: [u8; 32],
+        expected: [u8; 32],
+    },
+}
+
+impl fmt::Display for ParamsIntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamsIntegrityError::Io { path, error } => {
+                write!(f, "Cannot read params file {:?}: {}", path, error)
+            }
+            ParamsIntegrityError::IncorrectSize {
+                path,
+                actual,
+                expected,
+            } => write!(
+                f,
+                "Params file {:?} has {} bytes, the manifest expects {}",
+                path, actual, expected
+            ),
+            ParamsIntegrityError::IncorrectDigest {
+                path,
+                actual,
+                expected,
+            } => write!(
+                f,
+                "Params file {:?} has sha256 {}, the manifest expects {}",
+                path,
+                hex::encode(actual),
+                hex::encode(expected)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParamsIntegrityError {}
+
+impl ParamsManifest {
+    /// The manifest shipped in `params.manifest`.
+    pub fn builtin() -> &'static Self { &MANIFEST }
+
+    /// Parses `<file name> <size> <sha256>` lines, skipping blank lines and
+    /// `#` comments.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut entries = HashMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let malformed = |what: &str| {
+                format!("Line {} of the params manifest: {}", number + 1, what)
+            };
+            let [name, size, sha256] = line
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .try_into()
+                .map_err(|_| malformed("expected name, size and sha256"))?;
+            let size = size.parse().map_err(|_| malformed("malformed size"))?;
+            let sha256 = hex::decode(sha256)
+                .ok()
+                .and_then(|digest| digest.try_into().ok())
+                .ok_or_else(|| malformed("malformed sha256"))?;
+            entries.insert(name.to_string(), ManifestEntry { size, sha256 });
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn get(&self, file_name: &str) -> Option<&ManifestEntry> {
+        self.entries.get(file_name)
+    }
+
+    /// Checks the size, then the sha256, of a listed file. Unlisted files
+    /// pass.
+    pub fn check_file(&self, path: &Path) -> Result<(), ParamsIntegrityError> {
+        let Some(entry) = path
+            .file_name()
+            .and_then(|name| self.get(&name.to_string_lossy()))
+        else {
+            return Ok(());
+        };
+        let io_error = |error| ParamsIntegrityError::Io {
+            path: path.to_path_buf(),
+            error,
+        };
+
+        let file = File::open(path).map_err(io_error)?;
+        let size = file.metadata().map_err(io_error)?.len();
+        if size != entry.size {
+            return Err(ParamsIntegrityError::IncorrectSize {
+                path: path.to_path_buf(),
+                actual: size,
+                expected: entry.size,
+            });
+        }
+
+        let mut hasher = Sha256::new();
+        io::copy(&mut BufReader::with_capacity(1 << 20, file), &mut hasher)
+            .map_err(io_error)?;
+        let digest: [u8; 32] = hasher.finalize().into();
+        if digest != entry.sha256 {
+            return Err(ParamsIntegrityError::IncorrectDigest {
+                path: path.to_path_buf(),
+                actual: digest,
+                expected: entry.sha256,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Checks params files against the built-in manifest before the loaders
+/// deserialize them, and keeps the size and sha256 found for each listed file
+/// so they need not be hashed again. Clones share what was found.
+#[derive(Debug, Clone, Default)]
+pub struct ParamsChecker {
+    check: ParamsCheck,
+    checked: Arc<Mutex<HashMap<PathBuf, ManifestEntry>>>,
+}
+
+impl ParamsChecker {
+    pub fn new(check: ParamsCheck) -> Self {
+        Self {
+            check,
+            checked: Default::default(),
+        }
+    }
+
+    /// Size and sha256 of `path` as of its last check, if the manifest lists
+    /// it and it was hashed.
+    pub fn checked(&self, path: &Path) -> Option<ManifestEntry> {
+        self.checked.lock().unwrap().get(path).cloned()
+    }
+
+    /// Checks an existing params file against the built-in manifest.
+    pub(crate) fn check_file(
+        &self, path: &Path,
+    ) -> Result<(), ParamsIntegrityError> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let result = MANIFEST.check_file(path);
+        let listed = path
+            .file_name()
+            .and_then(|name| MANIFEST.get(&name.to_string_lossy()));
+        let found = match (&result, listed) {
+            (Ok(()), Some(entry)) => Some(entry.clone()),
+            (
+                Err(ParamsIntegrityError::IncorrectDigest { actual, .. }),
+                Some(entry),
+            ) => Some(ManifestEntry {
+                size: entry.size,
+                sha256: *actual,
+            }),
+            _ => None,
+        };
+        {
+            let mut checked = self.checked.lock().unwrap();
+            match found {
+                Some(entry) => checked.insert(path.to_path_buf(), entry),
+                None => checked.remove(path),
+            };
+        }
+
+        match result {
+            Err(e) if self.check == ParamsCheck::Warn => {
+                warn!("{}, loading it anyway", e);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+impl From<ParamsCheck> for ParamsChecker {
+    fn from(check: ParamsCheck) -> Self { Self::new(check) }
+}
+
+/// Reports a params file that could not be loaded as unreadable.
+pub(crate) fn load_error(
+    path: &Path, error: crate::error::Error,
+) -> ParamsIntegrityError {
+    let error = match error.0 {
+        crate::error::ErrorKind::File(error) => error,
+        kind => io::Error::new(io::ErrorKind::InvalidData, kind.to_string()),
+    };
+    ParamsIntegrityError::Io {
+        path: path.to_path_buf(),
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sha2::{Digest, Sha256};
+
+    use super::{ParamsIntegrityError, ParamsManifest};
+
+    #[test]
+    fn test_builtin_manifest() {
+        let manifest = ParamsManifest::builtin();
+        for coset in 0..3 {
+            let prove =
+                crate::amtp_file_name::<ark_bn254::Bn254>(20, 10, coset, true);
+            let verify =
+                crate::amtp_verify_file_name::<ark_bn254::Bn254>(20, 10, coset);
+            assert!(manifest.get(&prove).is_some(), "{prove}");
+            assert!(manifest.get(&verify).is_some(), "{verify}");
+        }
+        assert!(ParamsManifest::parse("params.bin 12").is_err());
+        assert!(ParamsManifest::parse("params.bin 12 0x00").is_err());
+    }
+
+    #[test]
+    fn test_check_file() {
+        let dir = std::env::temp_dir()
+            .join(format!("amt-manifest-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let contents = b"amt params";
+        let manifest = ParamsManifest::parse(&format!(
+            "# test\nparams.bin {} {}\n",
+            contents.len(),
+            hex::encode(Sha256::digest(contents))
+        ))
+        .unwrap();
+        let check = |name: &str, bytes: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, bytes).unwrap();
+            manifest.check_file(&path)
+        };
+
+        assert!(check("params.bin", contents).is_ok());
+        assert!(check("unlisted.bin", b"anything").is_ok());
+        assert!(matches!(
+            check("params.bin", &contents[..4]),
+            Err(ParamsIntegrityError::IncorrectSize {
+                actual: 4,
+                expected: 10,
+                ..
+            })
+        ));
+        assert!(matches!(
+            check("params.bin", b"AMT params"),
+            Err(ParamsIntegrityError::IncorrectDigest { .. })
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(not(feature = "cuda-bls12-381"))]
+    #[test]
+    fn test_params_check() {
+        use crate::{AMTVerifyParams, ParamsCheck, ParamsChecker};
+
+        let dir = std::env::temp_dir()
+            .join(format!("amt-params-check-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let name = crate::amtp_verify_file_name::<ark_bn254::Bn254>(20, 10, 0);
+        fs::write(dir.join(name), b"not params").unwrap();
+        let load = |check| {
+            AMTVerifyParams::<ark_bn254::Bn254>::from_dir_mont(
+                &dir, 20, 10, 0, &check,
+            )
+        };
+
+        assert!(matches!(
+            load(ParamsChecker::new(ParamsCheck::Enforce)),
+            Err(ParamsIntegrityError::IncorrectSize { .. })
+        ));
+        // Reads the file anyway, then misses the prover params to recover from
+        assert!(matches!(
+            load(ParamsChecker::new(ParamsCheck::Warn)),
+            Err(ParamsIntegrityError::Io { .. })
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
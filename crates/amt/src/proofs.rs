@@ -1,4 +1,4 @@
-use std::ops::Deref;
+use std::{fmt, ops::Deref};
 
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
@@ -51,3 +51,27 @@ pub enum AmtProofError {
     InconsistentCommitment,
     FailedLowDegreeTest,
 }
+
+impl fmt::Display for AmtProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmtProofError::UnexpectedDataLength => {
+                write!(f, "Data length does not match the proof")
+            }
+            AmtProofError::IncorrectPosition => {
+                write!(f, "Position is out of range")
+            }
+            AmtProofError::KzgError(depth) => {
+                write!(f, "KZG proof at depth {} does not verify", depth)
+            }
+            AmtProofError::InconsistentCommitment => {
+                write!(f, "Commitments of the proof do not add up")
+            }
+            AmtProofError::FailedLowDegreeTest => {
+                write!(f, "Low degree test failed")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AmtProofError {}
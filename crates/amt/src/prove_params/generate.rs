@@ -8,6 +8,7 @@ use crate::{
         G1, G2,
     },
     error,
+    manifest::{load_error, ParamsChecker, ParamsIntegrityError},
     power_tau::PowerTau,
     utils::{amtp_file_name, bitreverse, index_reverse},
 };
@@ -26,8 +27,8 @@ impl AMTParams<Bn254> {
     #[instrument(skip_all, name = "load_amt_params", level = 2, parent = None, fields(depth=depth, prove_depth=prove_depth, coset=coset))]
     pub fn from_dir_mont(
         dir: impl AsRef<Path>, depth: usize, prove_depth: usize, coset: usize,
-        create_mode: bool, pp: Option<&PowerTau<Bn254>>,
-    ) -> Self {
+        create_mode: bool, pp: Option<&PowerTau<Bn254>>, check: &ParamsChecker,
+    ) -> Result<Self, ParamsIntegrityError> {
         debug!(
             depth = depth,
             prove_depth = prove_depth,
@@ -37,20 +38,18 @@ impl AMTParams<Bn254> {
         let file_name =
             amtp_file_name::<Bn254>(depth, prove_depth, coset, true);
         let path = dir.as_ref().join(file_name);
+        check.check_file(&path)?;
 
         match Self::load_cached_mont(&path) {
             Ok(loaded) => {
-                return loaded;
+                return Ok(loaded);
             }
+            Err(e) if !create_mode => return Err(load_error(&path, e)),
             Err(e) => {
                 info!(?path, error = ?e, "Fail to load AMT params (mont format)");
             }
         }
 
-        if !create_mode {
-            panic!("Fail to load amt params in mont from {:?}", path);
-        }
-
         info!("Recover from unmont format");
 
         let params =
@@ -61,7 +60,7 @@ impl AMTParams<Bn254> {
         info!(file = ?path, "Save generated AMT params (mont format)");
         crate::fast_serde_bn254::write_amt_params(&params, writer).unwrap();
 
-        params
+        Ok(params)
     }
 
     fn load_cached_mont(file: impl AsRef<Path>) -> Result<Self, error::Error> {
@@ -3,7 +3,9 @@ use std::{fs::File, io::BufReader, path::Path};
 use crate::{
     amtp_verify_file_name,
     deferred_verification::{DeferredVerifier, PairingTask},
-    error, AMTParams,
+    error,
+    manifest::{ParamsChecker, ParamsIntegrityError},
+    AMTParams,
 };
 
 use crate::ec_algebra::{Fr, G1Aff, G2Aff, Pairing, G1};
@@ -31,8 +33,9 @@ pub struct AMTVerifyParams<PE: Pairing> {
 impl AMTVerifyParams<Bn254> {
     pub fn from_dir_mont(
         dir: impl AsRef<Path>, depth: usize, verify_depth: usize, coset: usize,
-    ) -> Self {
-        Self::from_dir_inner(&dir, depth, verify_depth, coset, || {
+        check: &ParamsChecker,
+    ) -> Result<Self, ParamsIntegrityError> {
+        Self::from_dir_inner(&dir, depth, verify_depth, coset, check, || {
             AMTParams::<Bn254>::from_dir_mont(
                 &dir,
                 depth,
@@ -40,6 +43,7 @@ impl AMTVerifyParams<Bn254> {
                 coset,
                 false,
                 None,
+                check,
             )
         })
     }
@@ -50,23 +54,32 @@ impl<PE: Pairing> AMTVerifyParams<PE> {
         dir: impl AsRef<Path>, expected_depth: usize, verify_depth: usize,
         coset: usize,
     ) -> Self {
-        Self::from_dir_inner(&dir, expected_depth, verify_depth, coset, || {
-            AMTParams::<PE>::from_dir(
-                &dir,
-                expected_depth,
-                verify_depth,
-                coset,
-                false,
-                None,
-            )
-        })
+        let params = Self::from_dir_inner(
+            &dir,
+            expected_depth,
+            verify_depth,
+            coset,
+            &ParamsChecker::default(),
+            || {
+                Ok(AMTParams::<PE>::from_dir(
+                    &dir,
+                    expected_depth,
+                    verify_depth,
+                    coset,
+                    false,
+                    None,
+                ))
+            },
+        );
+        params.unwrap_or_else(|e| panic!("{}", e))
     }
 
     #[instrument(skip_all, name = "load_amt_verify_params", level = 2, parent = None, fields(depth=expected_depth, verify_depth, coset))]
     fn from_dir_inner(
         dir: impl AsRef<Path>, expected_depth: usize, verify_depth: usize,
-        coset: usize, make_prover_params: impl Fn() -> AMTParams<PE>,
-    ) -> Self {
+        coset: usize, check: &ParamsChecker,
+        make_prover_params: impl Fn() -> Result<AMTParams<PE>, ParamsIntegrityError>,
+    ) -> Result<Self, ParamsIntegrityError> {
         debug!(
             depth = expected_depth,
             verify_depth, coset, "Load AMT verify params"
@@ -75,17 +88,18 @@ impl<PE: Pairing> AMTVerifyParams<PE> {
         let file_name =
             amtp_verify_file_name::<PE>(expected_depth, verify_depth, coset);
         let path = dir.as_ref().join(file_name);
+        check.check_file(&path)?;
 
         match Self::load_cached(&path) {
             Ok(loaded) => {
-                return loaded;
+                return Ok(loaded);
             }
             Err(e) => {
                 info!(?path, error = ?e, "Fail to load AMT verify params, recover from AMT params");
             }
         }
 
-        let amt_params = make_prover_params();
+        let amt_params = make_prover_params()?;
         let verify_params = Self {
             basis: amt_params.basis.clone(),
             vanishes: amt_params.vanishes[0..verify_depth].to_vec(),
@@ -98,7 +112,7 @@ impl<PE: Pairing> AMTVerifyParams<PE> {
         info!(file = ?path, "Save recovered AMT verify params");
         verify_params.serialize_uncompressed(&buffer).unwrap();
 
-        verify_params
+        Ok(verify_params)
     }
 
     fn load_cached(file: impl AsRef<Path>) -> Result<Self, error::Error> {
@@ -4,7 +4,7 @@ use crate::{
         COSET_N, PE, RAW_BLOB_SIZE,
     },
     raw_blob::RawBlob,
-    ZgEncoderParams,
+    EncoderError, ZgEncoderParams,
 };
 use amt::{ec_algebra::G1Aff, HalfBlob};
 use static_assertions::const_assert_eq;
@@ -15,15 +15,28 @@ pub struct EncodedBlobAMT([HalfBlob<PE, BLOB_COL_LOG, BLOB_ROW_LOG>; COSET_N]);
 const_assert_eq!(ZgEncoderParams::len(), RAW_BLOB_SIZE);
 
 impl EncodedBlobAMT {
-    #[tracing::instrument(skip_all, name = "encode_amt", level = 2)]
     pub fn build(raw_blob: &RawBlob, encoder_amt: &ZgEncoderParams) -> Self {
+        Self::build_cancellable(raw_blob, encoder_amt, || false).unwrap()
+    }
+
+    /// Gives up with `EncoderError::Cancelled` once `cancelled` is true,
+    /// checked before each coset FFT and proof generation.
+    #[tracing::instrument(skip_all, name = "encode_amt", level = 2)]
+    pub fn build_cancellable(
+        raw_blob: &RawBlob, encoder_amt: &ZgEncoderParams,
+        cancelled: impl Fn() -> bool,
+    ) -> Result<Self, EncoderError> {
         assert_eq!(raw_blob.len(), RAW_BLOB_SIZE);
 
-        let answer = Self(encoder_amt.process_blob(raw_blob));
+        let answer = Self(
+            encoder_amt
+                .process_blob_cancellable(raw_blob, cancelled)
+                .ok_or(EncoderError::Cancelled)?,
+        );
 
         answer.assert_commitment_consistent();
 
-        answer
+        Ok(answer)
     }
 
     #[cfg(feature = "parallel")]
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use amt::AmtProofError;
 
 #[derive(Debug, PartialEq, Eq)]
@@ -24,3 +26,51 @@ pub enum AmtError {
         error: AmtProofError,
     },
 }
+
+impl fmt::Display for AmtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmtError::IncorrectCommitment => {
+                write!(f, "Commitment does not match")
+            }
+            AmtError::IncorrectRowSize { actual, expected } => {
+                write!(f, "Row has {} elements, expected {}", actual, expected)
+            }
+            AmtError::RowIndexOverflow {
+                actual,
+                expected_max,
+            } => write!(
+                f,
+                "Row index {} is out of range, expected less than {}",
+                actual, expected_max
+            ),
+            AmtError::UnmatchedCosetIndex {
+                coset_index,
+                local_index,
+                amt_index,
+            } => write!(
+                f,
+                "Local index {} in coset {} does not match AMT index {}",
+                local_index, coset_index, amt_index
+            ),
+            AmtError::IncorrectProof {
+                coset_index,
+                amt_index,
+                error,
+            } => write!(
+                f,
+                "Incorrect proof for AMT index {} in coset {}: {}",
+                amt_index, coset_index, error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AmtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AmtError::IncorrectProof { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
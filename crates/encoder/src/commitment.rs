@@ -0,0 +1,128 @@
+use crate::{constants::G1A, CommitmentError};
+use ark_bn254::Fq;
+use ark_ec::AffineRepr;
+use ark_ff::Zero;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+/// Byte layouts of an erasure commitment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CommitmentFormat {
+    /// `x || y`, each an uncompressed little-endian arkworks `Fq`. The point
+    /// at infinity is `(0, 0)`.
+    #[default]
+    ArkUncompressed,
+    /// 32-byte arkworks compressed point, with the flags in the last byte.
+    ArkCompressed,
+    /// `uint256[2]` as taken by the EVM bn254 precompiles: `x || y`, each
+    /// 32 bytes big-endian. The point at infinity is `(0, 0)`.
+    EvmBigEndian,
+}
+
+impl CommitmentFormat {
+    pub const fn size(self) -> usize {
+        match self {
+            CommitmentFormat::ArkCompressed => 32,
+            CommitmentFormat::ArkUncompressed
+            | CommitmentFormat::EvmBigEndian => 64,
+        }
+    }
+}
+
+pub fn commitment_to_bytes(
+    commitment: &G1A, format: CommitmentFormat,
+) -> Vec<u8> {
+    let mut answer = Vec::with_capacity(format.size());
+    match format {
+        CommitmentFormat::ArkCompressed => {
+            commitment.serialize_compressed(&mut answer).unwrap();
+        }
+        CommitmentFormat::ArkUncompressed | CommitmentFormat::EvmBigEndian => {
+            let zero = Fq::zero();
+            let (x, y) = commitment.xy().unwrap_or((&zero, &zero));
+            for coordinate in [x, y] {
+                let start = answer.len();
+                coordinate.serialize_uncompressed(&mut answer).unwrap();
+                if format == CommitmentFormat::EvmBigEndian {
+                    answer[start..].reverse();
+                }
+            }
+        }
+    }
+    answer
+}
+
+/// Reads a commitment and checks it is a point of the G1 subgroup.
+pub fn commitment_from_bytes(
+    bytes: &[u8], format: CommitmentFormat,
+) -> Result<G1A, CommitmentError> {
+    if bytes.len() != format.size() {
+        return Err(CommitmentError::IncorrectSize {
+            actual: bytes.len(),
+            expected: format.size(),
+        });
+    }
+    if format == CommitmentFormat::ArkCompressed {
+        return G1A::deserialize_compressed(bytes)
+            .map_err(|e| CommitmentError::Malformed(e.to_string()));
+    }
+
+    let read_coordinate = |raw: &[u8]| {
+        let mut raw = raw.to_vec();
+        if format == CommitmentFormat::EvmBigEndian {
+            raw.reverse();
+        }
+        Fq::deserialize_uncompressed(&raw[..])
+            .map_err(|e| CommitmentError::Malformed(e.to_string()))
+    };
+    let x = read_coordinate(&bytes[..32])?;
+    let y = read_coordinate(&bytes[32..])?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1A::zero());
+    }
+    let point = G1A::new_unchecked(x, y);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve()
+    {
+        return Err(CommitmentError::NotOnCurve);
+    }
+    Ok(point)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{commitment_from_bytes, commitment_to_bytes, CommitmentFormat};
+    use crate::{constants::G1A, CommitmentError};
+    use ark_ec::{AffineRepr, CurveGroup};
+    use ark_std::UniformRand;
+    use test_case::test_case;
+
+    #[test_case(CommitmentFormat::ArkUncompressed; "ark uncompressed")]
+    #[test_case(CommitmentFormat::ArkCompressed; "ark compressed")]
+    #[test_case(CommitmentFormat::EvmBigEndian; "evm big endian")]
+    fn test_round_trip(format: CommitmentFormat) {
+        let mut rng = ark_std::test_rng();
+        let point = crate::constants::G1Curve::rand(&mut rng).into_affine();
+        for commitment in [point, G1A::zero()] {
+            let bytes = commitment_to_bytes(&commitment, format);
+            assert_eq!(bytes.len(), format.size());
+            assert_eq!(commitment_from_bytes(&bytes, format), Ok(commitment));
+        }
+    }
+
+    #[test]
+    fn test_evm_layout() {
+        let generator = G1A::generator();
+        let bytes =
+            commitment_to_bytes(&generator, CommitmentFormat::EvmBigEndian);
+        // The bn254 generator is (1, 2)
+        assert_eq!(bytes[31], 1);
+        assert_eq!(bytes[63], 2);
+        assert!(bytes[..31].iter().chain(&bytes[32..63]).all(|b| *b == 0));
+
+        let mut invalid = bytes.clone();
+        invalid[63] = 3;
+        assert_eq!(
+            commitment_from_bytes(&invalid, CommitmentFormat::EvmBigEndian),
+            Err(CommitmentError::NotOnCurve)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use super::slice::EncodedSlice;
+use super::{error::EncoderError, slice::EncodedSlice};
 use crate::{
     amt::blob::EncodedBlobAMT,
     constants::{G1Curve, BLOB_ROW_ENCODED, COSET_N, G1A, RAW_BLOB_SIZE},
@@ -30,15 +30,32 @@ pub struct EncodedBlob {
 
 impl EncodedBlob {
     pub fn build(raw_blob: &RawBlob, encoder_amt: &ZgEncoderParams) -> Self {
+        Self::build_cancellable(raw_blob, encoder_amt, || false).unwrap()
+    }
+
+    /// Like `build`, but calls `cancelled` between the stages (each coset
+    /// FFT, each `HalfBlob::generate`, the merkle tree) and gives up with
+    /// `EncoderError::Cancelled` once it is true.
+    pub fn build_cancellable(
+        raw_blob: &RawBlob, encoder_amt: &ZgEncoderParams,
+        cancelled: impl Fn() -> bool,
+    ) -> Result<Self, EncoderError> {
         assert_eq!(raw_blob.len(), RAW_BLOB_SIZE);
 
-        let amt = EncodedBlobAMT::build(raw_blob, encoder_amt);
+        let amt = EncodedBlobAMT::build_cancellable(
+            raw_blob,
+            encoder_amt,
+            &cancelled,
+        )?;
+        if cancelled() {
+            return Err(EncoderError::Cancelled);
+        }
 
         let blob_h256: Vec<_> =
             amt.iter_blob().cloned().map(scalar_to_h256).collect();
         let merkle = EncodedBlobMerkle::build(blob_h256);
 
-        Self { amt, merkle }
+        Ok(Self { amt, merkle })
     }
 
     pub fn get_row(&self, index: usize) -> EncodedSlice {
@@ -245,11 +262,18 @@ mod tests {
     use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
     use test_case::test_case;
 
-    static ENCODER: Lazy<ZgEncoderParams> =
-        Lazy::new(|| EncoderParams::from_dir_mont("../amt/pp", true, None));
+    static ENCODER: Lazy<ZgEncoderParams> = Lazy::new(|| {
+        EncoderParams::from_dir_mont(
+            "../amt/pp",
+            true,
+            None,
+            &Default::default(),
+        )
+        .unwrap()
+    });
     static SIGNER: Lazy<ZgSignerParams> = Lazy::new(|| {
         let _ = &*ENCODER;
-        VerifierParams::from_dir_mont("../amt/pp")
+        VerifierParams::from_dir_mont("../amt/pp", &Default::default()).unwrap()
     });
 
     fn gen_encoded_blob(num_bytes: usize) -> Result<EncodedBlob, EncoderError> {
@@ -1,8 +1,15 @@
+use std::fmt;
+
 pub use crate::{amt::error::AmtError, merkle::error::MerkleError};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum EncoderError {
-    TooLargeBlob { actual: usize, expected_max: usize },
+    TooLargeBlob {
+        actual: usize,
+        expected_max: usize,
+    },
+    /// The caller gave up, see `EncodedBlob::build_cancellable`.
+    Cancelled,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -20,8 +27,88 @@ pub enum VerifierError {
     },
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommitmentError {
+    IncorrectSize { actual: usize, expected: usize },
+    Malformed(String),
+    NotOnCurve,
+}
+
+impl fmt::Display for EncoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncoderError::TooLargeBlob {
+                actual,
+                expected_max,
+            } => write!(
+                f,
+                "Blob is too large: {} bytes, at most {} bytes",
+                actual, expected_max
+            ),
+            EncoderError::Cancelled => write!(f, "Encoding was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for EncoderError {}
+
+impl fmt::Display for CommitmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommitmentError::IncorrectSize { actual, expected } => write!(
+                f,
+                "Commitment has {} bytes, expected {}",
+                actual, expected
+            ),
+            CommitmentError::Malformed(e) => {
+                write!(f, "Malformed commitment: {}", e)
+            }
+            CommitmentError::NotOnCurve => {
+                write!(f, "Commitment is not a valid G1 point")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommitmentError {}
+
+impl fmt::Display for VerifierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifierError::AMT(e) => write!(f, "AMT check failed: {}", e),
+            VerifierError::Merkle(e) => write!(f, "Merkle check failed: {}", e),
+            VerifierError::UnmatchedAMTIndex {
+                row_index,
+                amt_index,
+            } => write!(
+                f,
+                "Row index {} does not match AMT index {}",
+                row_index, amt_index
+            ),
+            VerifierError::UnmatchedMerkleIndex {
+                row_index,
+                merkle_index,
+            } => write!(
+                f,
+                "Row index {} does not match Merkle index {}",
+                row_index, merkle_index
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifierError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VerifierError::AMT(e) => Some(e),
+            VerifierError::Merkle(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl From<EncoderError> for String {
-    fn from(error: EncoderError) -> String { format!("{:?}", error) }
+    fn from(error: EncoderError) -> String { error.to_string() }
 }
 
 impl From<AmtError> for VerifierError {
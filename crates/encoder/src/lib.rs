@@ -4,25 +4,36 @@ extern crate amt as amt_crate;
 extern crate ark_std;
 
 mod amt;
+mod commitment;
 pub mod constants;
 mod encoder;
 mod merkle;
 mod raw_blob;
 mod raw_data;
+mod self_test;
 mod utils;
 
 pub use amt_crate::DeferredVerifier;
 
 pub use amt::{blob::EncodedBlobAMT, slice::EncodedSliceAMT};
+pub use commitment::{
+    commitment_from_bytes, commitment_to_bytes, CommitmentFormat,
+};
 pub use encoder::{
-    blob::EncodedBlob,
-    error::{EncoderError, VerifierError},
+    blob::{compute_file_root, EncodedBlob},
+    error::{
+        AmtError, CommitmentError, EncoderError, MerkleError, VerifierError,
+    },
     light_slice::LightEncodedSlice,
     slice::EncodedSlice,
 };
 pub use merkle::{blob::EncodedBlobMerkle, slice::EncodedSliceMerkle};
 pub use raw_blob::RawBlob;
-pub use raw_data::RawData;
+pub use raw_data::{RawData, RawDataBuilder};
+pub use self_test::{
+    self_test, self_test_payload, SelfTestAnswer, SelfTestError,
+    SELF_TEST_PAYLOAD_SIZE,
+};
 pub use utils::{raw_unit_to_scalar, scalar_to_h256};
 
 pub type ZgEncoderParams = ::amt::EncoderParams<
@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum MerkleError {
     IncorrectRoot,
@@ -6,3 +8,32 @@ pub enum MerkleError {
     IncorrectLocalRoot { row_index: usize },
     IncorrectProof { row_index: usize },
 }
+
+impl fmt::Display for MerkleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MerkleError::IncorrectRoot => {
+                write!(f, "Storage root does not match")
+            }
+            MerkleError::IncorrectSize { actual, expected } => {
+                write!(f, "Row has {} elements, expected {}", actual, expected)
+            }
+            MerkleError::RowIndexOverflow {
+                actual,
+                expected_max,
+            } => write!(
+                f,
+                "Row index {} is out of range, expected less than {}",
+                actual, expected_max
+            ),
+            MerkleError::IncorrectLocalRoot { row_index } => {
+                write!(f, "Incorrect local root for row {}", row_index)
+            }
+            MerkleError::IncorrectProof { row_index } => {
+                write!(f, "Incorrect Merkle proof for row {}", row_index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MerkleError {}
@@ -56,18 +56,76 @@ impl TryFrom<&[u8]> for RawData {
     type Error = EncoderError;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let value_len = value.len();
-        if value_len <= MAX_RAW_DATA_SIZE {
-            let mut array = vec![0u8; MAX_BLOB_SIZE];
-            array[..value_len].copy_from_slice(value);
-            array[MAX_RAW_DATA_SIZE..]
-                .copy_from_slice(&(value_len as u32).to_le_bytes());
-            Ok(RawData(array))
-        } else {
-            Err(EncoderError::TooLargeBlob {
-                actual: value.len(),
+        let mut builder = RawDataBuilder::new();
+        builder.extend_from_slice(value)?;
+        Ok(builder.build())
+    }
+}
+
+/// Assembles a [`RawData`] from a sequence of chunks, writing each chunk
+/// straight into the padded buffer.
+#[derive(Default)]
+pub struct RawDataBuilder {
+    data: RawData,
+    length: usize,
+}
+
+impl RawDataBuilder {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn len(&self) -> usize { self.length }
+
+    pub fn is_empty(&self) -> bool { self.length == 0 }
+
+    /// Appends `chunk` to the payload, failing as soon as the running size
+    /// exceeds `MAX_RAW_DATA_SIZE`.
+    pub fn extend_from_slice(
+        &mut self, chunk: &[u8],
+    ) -> Result<(), EncoderError> {
+        let new_length = self.length.saturating_add(chunk.len());
+        if new_length > MAX_RAW_DATA_SIZE {
+            return Err(EncoderError::TooLargeBlob {
+                actual: new_length,
                 expected_max: MAX_RAW_DATA_SIZE,
-            })
+            });
         }
+        self.data[self.length..new_length].copy_from_slice(chunk);
+        self.length = new_length;
+        Ok(())
+    }
+
+    pub fn build(mut self) -> RawData {
+        self.data[MAX_RAW_DATA_SIZE..]
+            .copy_from_slice(&(self.length as u32).to_le_bytes());
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RawData, RawDataBuilder};
+    use crate::{constants::MAX_RAW_DATA_SIZE, encoder::error::EncoderError};
+    use rand::{thread_rng, RngCore};
+    use test_case::test_case;
+
+    #[test_case(0, 1 => Ok(()); "empty data")]
+    #[test_case(1234, 100 => Ok(()); "normal sized data")]
+    #[test_case(MAX_RAW_DATA_SIZE, 4096 => Ok(()); "exact sized data")]
+    #[test_case(MAX_RAW_DATA_SIZE + 1, 4096 => Err(EncoderError::TooLargeBlob { actual: MAX_RAW_DATA_SIZE + 1, expected_max: MAX_RAW_DATA_SIZE }); "overflow sized data")]
+    fn test_chunked_build(
+        num_bytes: usize, chunk_size: usize,
+    ) -> Result<(), EncoderError> {
+        let mut data = vec![0u8; num_bytes];
+        thread_rng().fill_bytes(&mut data[..]);
+
+        let mut builder = RawDataBuilder::new();
+        for chunk in data.chunks(chunk_size) {
+            builder.extend_from_slice(chunk)?;
+        }
+        let raw_data = builder.build();
+
+        assert_eq!(raw_data, RawData::try_from(&data[..])?);
+        assert_eq!(raw_data.as_bytes(), &data[..]);
+        Ok(())
     }
 }
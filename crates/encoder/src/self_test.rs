@@ -0,0 +1,164 @@
+use std::fmt;
+
+use crate::{
+    commitment_to_bytes,
+    constants::{BLOB_COL_LOG, BLOB_ROW_ENCODED, BLOB_ROW_LOG, COSET_N},
+    CommitmentFormat, EncodedBlob, RawData, VerifierError, ZgEncoderParams,
+    ZgSignerParams,
+};
+
+/// Size of `self_test_payload`.
+pub const SELF_TEST_PAYLOAD_SIZE: usize = 4096;
+
+/// `(BLOB_ROW_LOG, BLOB_COL_LOG, COSET_N)`, then the hex erasure commitment
+/// (`CommitmentFormat::EvmBigEndian`) and storage root of `self_test_payload`
+/// under the published params, as printed by `cli self-test`. Test params use
+/// a random tau and have no known answer; without one the encoder starts with
+/// a warning.
+const KNOWN_ANSWERS: &[((usize, usize, usize), &str, &str)] = &[];
+
+/// Encoding of `self_test_payload`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestAnswer {
+    pub erasure_commitment: Vec<u8>,
+    pub storage_root: [u8; 32],
+}
+
+impl SelfTestAnswer {
+    /// The answer recorded for the compiled geometry, if any.
+    pub fn known() -> Option<Self> {
+        let geometry = (BLOB_ROW_LOG, BLOB_COL_LOG, COSET_N);
+        let (_, commitment, root) = KNOWN_ANSWERS
+            .iter()
+            .find(|(answer_geometry, ..)| *answer_geometry == geometry)?;
+        Some(Self {
+            erasure_commitment: hex::decode(commitment)
+                .expect("known answers are hex"),
+            storage_root: hex::decode(root)
+                .ok()
+                .and_then(|root| root.try_into().ok())
+                .expect("known answers have 32-byte roots"),
+        })
+    }
+}
+
+impl fmt::Display for SelfTestAnswer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "erasure commitment {}, storage root {}",
+            hex::encode(&self.erasure_commitment),
+            hex::encode(self.storage_root)
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum SelfTestError {
+    IncorrectAnswer {
+        actual: SelfTestAnswer,
+        expected: SelfTestAnswer,
+    },
+    InvalidSlice {
+        index: usize,
+        error: VerifierError,
+    },
+}
+
+impl fmt::Display for SelfTestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelfTestError::IncorrectAnswer { actual, expected } => write!(
+                f,
+                "Self-test encoded to {}, expected {}",
+                actual, expected
+            ),
+            SelfTestError::InvalidSlice { index, error } => {
+                write!(f, "Self-test row {} does not verify: {}", index, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SelfTestError {}
+
+/// A fixed payload of `SELF_TEST_PAYLOAD_SIZE` bytes.
+pub fn self_test_payload() -> Vec<u8> {
+    (0..SELF_TEST_PAYLOAD_SIZE as u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+        .collect()
+}
+
+/// Encodes `self_test_payload`, checks its first and last rows against
+/// `verifier` when given, and the result against `expected` when given.
+pub fn self_test(
+    params: &ZgEncoderParams, verifier: Option<&ZgSignerParams>,
+    expected: Option<&SelfTestAnswer>,
+) -> Result<SelfTestAnswer, SelfTestError> {
+    let raw_data = RawData::try_from(&self_test_payload()[..])
+        .expect("the self-test payload fits in a blob");
+    let encoded_blob = EncodedBlob::build(&raw_data.into(), params);
+    let actual = SelfTestAnswer {
+        erasure_commitment: commitment_to_bytes(
+            &encoded_blob.get_affine_commitment(),
+            CommitmentFormat::EvmBigEndian,
+        ),
+        storage_root: encoded_blob.get_file_root(),
+    };
+
+    if let Some(verifier) = verifier {
+        let commitment = encoded_blob.get_commitment();
+        for index in [0, BLOB_ROW_ENCODED - 1] {
+            encoded_blob
+                .get_row(index)
+                .verify(verifier, &commitment, &actual.storage_root, None)
+                .map_err(|error| SelfTestError::InvalidSlice {
+                    index,
+                    error,
+                })?;
+        }
+    }
+    match expected {
+        Some(expected) if *expected != actual => {
+            Err(SelfTestError::IncorrectAnswer {
+                actual,
+                expected: expected.clone(),
+            })
+        }
+        _ => Ok(actual),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amt::{EncoderParams, VerifierParams};
+
+    use super::{self_test, SelfTestAnswer, SelfTestError};
+    use crate::{ZgEncoderParams, ZgSignerParams};
+
+    #[test]
+    fn test_self_test() {
+        let encoder: ZgEncoderParams = EncoderParams::from_dir_mont(
+            "../amt/pp",
+            true,
+            None,
+            &Default::default(),
+        )
+        .unwrap();
+        let signer: ZgSignerParams =
+            VerifierParams::from_dir_mont("../amt/pp", &Default::default())
+                .unwrap();
+        // Test params have a random tau
+        assert_eq!(SelfTestAnswer::known(), None);
+
+        let answer = self_test(&encoder, Some(&signer), None).unwrap();
+        assert_eq!(self_test(&encoder, None, Some(&answer)).unwrap(), answer);
+
+        let mut expected = answer;
+        expected.storage_root[0] ^= 1;
+        assert!(matches!(
+            self_test(&encoder, None, Some(&expected)),
+            Err(SelfTestError::IncorrectAnswer { .. })
+        ));
+    }
+}
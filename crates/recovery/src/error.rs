@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum RecoveryErr {
     ExtaustiveK,
@@ -5,3 +7,24 @@ pub enum RecoveryErr {
     RowIdOverflow,
     TooFewRowIds,
 }
+
+impl fmt::Display for RecoveryErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecoveryErr::ExtaustiveK => {
+                write!(f, "No usable coset shift found for recovery")
+            }
+            RecoveryErr::InvalidLength => {
+                write!(f, "A row has an invalid length")
+            }
+            RecoveryErr::RowIdOverflow => {
+                write!(f, "A row index is out of range")
+            }
+            RecoveryErr::TooFewRowIds => {
+                write!(f, "Not enough rows to recover")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RecoveryErr {}
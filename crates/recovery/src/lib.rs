@@ -8,4 +8,5 @@ mod poly;
 mod utils;
 mod zpoly;
 
-pub use utils::recover_from_da_slice;
+pub use error::RecoveryErr;
+pub use utils::{recover_from_da_slice, recover_from_lines};
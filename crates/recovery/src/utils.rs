@@ -27,9 +27,8 @@ use zg_encoder::RawData;
 pub fn recover_from_da_slice(
     slices: &BTreeMap<usize, Vec<u8>>,
 ) -> Result<Vec<u8>, String> {
-    use data_poly::data_poly;
     use raw_slice_to_line;
-    use zg_encoder::constants::{Scalar, BLOB_ROW_N};
+    use zg_encoder::constants::Scalar;
 
     let converted_lines: BTreeMap<usize, Vec<Scalar>> = slices
         .iter()
@@ -43,12 +42,21 @@ pub fn recover_from_da_slice(
         info!("{:?} lines dropped because of incorrect format", dropped);
     }
 
-    if converted_lines.len() < BLOB_ROW_N {
+    recover_from_lines(&converted_lines)
+}
+
+pub fn recover_from_lines(
+    lines: &BTreeMap<usize, Vec<Scalar>>,
+) -> Result<Vec<u8>, String> {
+    use data_poly::data_poly;
+    use zg_encoder::constants::BLOB_ROW_N;
+
+    if lines.len() < BLOB_ROW_N {
         return Err("Not enough valid lines".to_string());
     }
 
-    let raw_blob = data_poly(&converted_lines)
-        .map_err(|e| format!("Cannot recover data: {:?}", e))?;
+    let raw_blob =
+        data_poly(lines).map_err(|e| format!("Cannot recover data: {}", e))?;
 
     let raw_data: RawData = raw_blob.try_into()?;
     Ok(raw_data.as_bytes().to_vec())
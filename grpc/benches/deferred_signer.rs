@@ -41,8 +41,15 @@ fn main() {
 
     info!("load params");
     let param_dir = "../crates/amt/pp";
-    let params = ZgEncoderParams::from_dir_mont(param_dir, true, None);
-    let ver_params = ZgSignerParams::from_dir_mont(param_dir);
+    let params = ZgEncoderParams::from_dir_mont(
+        param_dir,
+        true,
+        None,
+        &Default::default(),
+    )
+    .unwrap();
+    let ver_params =
+        ZgSignerParams::from_dir_mont(param_dir, &Default::default()).unwrap();
 
     bench_no_defer(&params, &ver_params);
     bench_defer(&params, &ver_params);
@@ -23,7 +23,7 @@ fn to_encoded_amt(
     let raw_data: RawData = data[..].try_into().unwrap();
     let raw_blob: RawBlob = raw_data.try_into().unwrap();
     let encoded_blob =
-        EncodedBlobAMT::build(&raw_blob, &encoder_service.params);
+        EncodedBlobAMT::build(&raw_blob, &encoder_service.params());
     encoded_blob
 }
 
@@ -55,7 +55,7 @@ fn to_encoded_blob(
 ) -> EncodedBlob {
     let raw_data: RawData = data[..].try_into().unwrap();
     let raw_blob: RawBlob = raw_data.into();
-    let encoded_blob = EncodedBlob::build(&raw_blob, &encoder_service.params);
+    let encoded_blob = EncodedBlob::build(&raw_blob, &encoder_service.params());
     encoded_blob
 }
 
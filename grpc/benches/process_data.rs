@@ -38,7 +38,13 @@ fn main() {
 
     let start = Instant::now();
 
-    let params = ZgEncoderParams::from_dir_mont("../crates/amt/pp", true, None);
+    let params = ZgEncoderParams::from_dir_mont(
+        "../crates/amt/pp",
+        true,
+        None,
+        &Default::default(),
+    )
+    .unwrap();
     info!("Load time elapsed {:?}", start.elapsed());
 
     params.warmup();
@@ -62,7 +68,7 @@ fn bench_amt(params: ZgEncoderParams) {
 }
 
 fn bench_all(params: ZgEncoderParams) {
-    let encoder = EncoderService { params };
+    let encoder = EncoderService::from_params(params);
     let mut data = vec![0u8; MAX_BLOB_SIZE];
     thread_rng().fill(&mut data[..]);
 
@@ -49,7 +49,8 @@ fn criterion_benchmark(c: &mut Criterion) {
         .collect();
     let num_slice = encoded_slice.len();
 
-    let signer_service = SignerService::new(param_dir);
+    let signer_service =
+        SignerService::new(param_dir, &Default::default()).unwrap();
 
     let mut group = c.benchmark_group("signer");
     for i in [0usize, 3, 10, 37, num_slice - 1] {
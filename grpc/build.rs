@@ -9,6 +9,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .protoc_arg("--experimental_allow_proto3_optional") // for older systems
         .build_client(true)
         .build_server(true)
+        .type_attribute(".encoder", "#[derive(serde::Serialize)]")
         .file_descriptor_set_path(out_dir.join("encoder_descriptor.bin"))
         .compile(&["proto/encoder.proto"], &["proto"])?;
 
@@ -0,0 +1,343 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Instant, UNIX_EPOCH},
+};
+
+use amt::{
+    amtp_file_name, amtp_verify_file_name, EncoderParams, ParamsChecker,
+    ParamsManifest,
+};
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+use zg_encoder::constants::{BLOB_COL_LOG, BLOB_ROW_LOG, COSET_N, PE};
+
+use crate::{
+    error::ServiceError,
+    executor::Executor,
+    metrics,
+    service::{
+        encoder::{
+            admin_server::Admin, GetInfoReply, GetInfoRequest, GetStatusReply,
+            GetStatusRequest, LastError, ParamsFile, ReloadParamsReply,
+            ReloadParamsRequest,
+        },
+        warmup_params,
+    },
+    EncoderService, SignerService,
+};
+
+pub use crate::service::encoder::admin_server::AdminServer;
+
+/// Cargo features of this build that change how encodes run.
+const FEATURES: &[(&str, bool)] = &[
+    ("parallel", cfg!(feature = "parallel")),
+    ("cuda", cfg!(feature = "cuda")),
+    ("cuda-verifier", cfg!(feature = "cuda-verifier")),
+];
+
+/// Reports the build, params and load of the server, and reloads the encoder
+/// params when `with_reload` allows it.
+#[derive(Clone)]
+pub struct AdminService {
+    param_dir: PathBuf,
+    started: Instant,
+    executor: Executor,
+    encoder: Option<EncoderService>,
+    signer: Option<SignerService>,
+    services: Vec<String>,
+    allow_reload: bool,
+    params_checker: ParamsChecker,
+    reloading: Arc<AtomicBool>,
+}
+
+impl AdminService {
+    /// Reports the queue of `executor`, which should be the encoder's.
+    pub fn new(
+        param_dir: &str, executor: Executor, encoder: Option<EncoderService>,
+        signer: Option<SignerService>,
+    ) -> Self {
+        Self {
+            param_dir: param_dir.into(),
+            started: Instant::now(),
+            executor,
+            encoder,
+            signer,
+            services: vec![],
+            allow_reload: false,
+            params_checker: ParamsChecker::default(),
+            reloading: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Counts uptime from `started` instead of the creation of the service.
+    pub fn with_started(mut self, started: Instant) -> Self {
+        self.started = started;
+        self
+    }
+
+    /// Names of the served services, as reported by `GetInfo`.
+    pub fn with_services(mut self, services: &[&str]) -> Self {
+        self.services = services.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    pub fn with_reload(mut self, allow_reload: bool) -> Self {
+        self.allow_reload = allow_reload;
+        self
+    }
+
+    /// Reports the digests `params_checker` found when the services loaded
+    /// their params, and checks reloaded params with it.
+    pub fn with_params_checker(
+        mut self, params_checker: ParamsChecker,
+    ) -> Self {
+        self.params_checker = params_checker;
+        self
+    }
+
+    fn params_files(&self) -> Result<Vec<ParamsFile>, ServiceError> {
+        params_file_names(self.encoder.is_some(), self.signer.is_some())
+            .into_iter()
+            .map(|name| {
+                params_file(&self.param_dir, name, &self.params_checker)
+            })
+            .collect::<io::Result<Vec<_>>>()
+            .map_err(|e| ServiceError::Params(e.to_string()))
+    }
+}
+
+#[tonic::async_trait]
+impl Admin for AdminService {
+    async fn get_info(
+        &self, _request: Request<GetInfoRequest>,
+    ) -> Result<Response<GetInfoReply>, Status> {
+        Ok(Response::new(GetInfoReply {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            backend: backend().to_string(),
+            features: FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            blob_row_log: BLOB_ROW_LOG as u32,
+            blob_col_log: BLOB_COL_LOG as u32,
+            coset_n: COSET_N as u32,
+            params_dir: self.param_dir.display().to_string(),
+            params_files: self.params_files()?,
+            services: self.services.clone(),
+            uptime_secs: self.started.elapsed().as_secs(),
+        }))
+    }
+
+    async fn get_status(
+        &self, _request: Request<GetStatusRequest>,
+    ) -> Result<Response<GetStatusReply>, Status> {
+        let last_error = metrics::last_error().map(|error| LastError {
+            method: error.method,
+            code: format!("{:?}", error.code),
+            message: error.message,
+            unix_secs: error
+                .time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        });
+        Ok(Response::new(GetStatusReply {
+            in_flight: self.executor.in_flight() as u64,
+            queue_length: self.executor.queue_depth() as u64,
+            jobs: self
+                .encoder
+                .as_ref()
+                .map_or(0, |encoder| encoder.job_count() as u64),
+            reloading: self.reloading.load(Ordering::Acquire),
+            last_error,
+        }))
+    }
+
+    /// Loads the encoder params again and swaps them in once they pass the
+    /// self-test. Encodes keep running on the old params meanwhile, so both
+    /// are held in memory until those finish.
+    async fn reload_params(
+        &self, _request: Request<ReloadParamsRequest>,
+    ) -> Result<Response<ReloadParamsReply>, Status> {
+        let Some(encoder) = self.encoder.clone() else {
+            return Err(ServiceError::ReloadRejected(
+                "the encoder service is disabled".into(),
+            )
+            .into());
+        };
+        if !self.allow_reload {
+            return Err(ServiceError::ReloadRejected(
+                "allow_params_reload is off".into(),
+            )
+            .into());
+        }
+        if self.reloading.swap(true, Ordering::AcqRel) {
+            return Err(ServiceError::ReloadInProgress.into());
+        }
+        // Held by the blocking task, which runs on if the client gives up
+        let guard = ReloadGuard(self.reloading.clone());
+        let param_dir = self.param_dir.clone();
+        let verifier = self.signer.as_ref().map(|signer| signer.params.clone());
+        let check = self.params_checker.clone();
+        info!(?param_dir, "Reloading encoder params");
+
+        let reloaded = tokio::task::spawn_blocking(move || {
+            let _guard = guard;
+            let started = Instant::now();
+            let params =
+                EncoderParams::from_dir_mont(&param_dir, false, None, &check)
+                    .map_err(|e| e.to_string())?;
+            warmup_params(&params, verifier.as_deref())
+                .map_err(|e| e.to_string())?;
+            encoder.replace_params(params);
+            Ok::<_, String>(started.elapsed())
+        })
+        .await;
+        match reloaded {
+            Ok(Ok(elapsed)) => {
+                info!(?elapsed, "Encoder params reloaded");
+                Ok(Response::new(ReloadParamsReply {
+                    load_secs: elapsed.as_secs_f64(),
+                }))
+            }
+            Ok(Err(e)) => Err(reload_failed(e)),
+            Err(e) => Err(reload_failed(e.to_string())),
+        }
+    }
+}
+
+fn reload_failed(error: String) -> Status {
+    warn!(%error, "Params reload failed, keeping the old params");
+    ServiceError::Params(error).into()
+}
+
+struct ReloadGuard(Arc<AtomicBool>);
+
+impl Drop for ReloadGuard {
+    fn drop(&mut self) { self.0.store(false, Ordering::Release); }
+}
+
+fn backend() -> &'static str {
+    if cfg!(feature = "cuda") {
+        "cuda"
+    } else if cfg!(feature = "parallel") {
+        "parallel"
+    } else {
+        "cpu"
+    }
+}
+
+/// Files loaded by `EncoderParams::from_dir_mont` and
+/// `VerifierParams::from_dir_mont` for the compiled geometry.
+fn params_file_names(encoder: bool, signer: bool) -> Vec<String> {
+    let depth = BLOB_ROW_LOG + BLOB_COL_LOG;
+    let mut names = vec![];
+    for coset in 0..COSET_N {
+        if encoder {
+            names.push(amtp_file_name::<PE>(depth, BLOB_ROW_LOG, coset, true));
+        }
+        if signer {
+            names.push(amtp_verify_file_name::<PE>(depth, BLOB_ROW_LOG, coset));
+        }
+    }
+    names
+}
+
+fn params_file(
+    dir: &Path, name: String, checker: &ParamsChecker,
+) -> io::Result<ParamsFile> {
+    let path = dir.join(&name);
+    let size = match path.metadata() {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(ParamsFile {
+                name,
+                ..Default::default()
+            });
+        }
+        Err(e) => return Err(e),
+    };
+    let checked = checker.checked(&path).filter(|found| found.size == size);
+    let entry = ParamsManifest::builtin().get(&name);
+    Ok(ParamsFile {
+        exists: true,
+        size,
+        sha256: checked
+            .as_ref()
+            .map_or(vec![], |found| found.sha256.to_vec()),
+        in_manifest: entry.is_some(),
+        matches_manifest: entry.is_some() && checked.as_ref() == entry,
+        name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::{Code, Request};
+
+    use super::{Admin, AdminService};
+    use crate::{
+        error_info,
+        service::encoder::{
+            GetInfoRequest, GetStatusRequest, ReloadParamsRequest,
+        },
+        EncoderService, Executor, SignerService,
+    };
+
+    const PARAM_DIR: &str = "../crates/amt/pp";
+
+    #[tokio::test]
+    async fn test_admin() {
+        let encoder = EncoderService::new_for_test(PARAM_DIR);
+        let signer =
+            SignerService::new(PARAM_DIR, &Default::default()).unwrap();
+        let admin = AdminService::new(
+            PARAM_DIR,
+            Executor::new(&Default::default()),
+            Some(encoder.clone()),
+            Some(signer),
+        );
+
+        let info = admin
+            .get_info(Request::new(GetInfoRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(info.coset_n, 3);
+        assert_eq!(info.params_files.len(), 6);
+        let prove = &info.params_files[0];
+        assert!(prove.name.starts_with("amt-prove-coset0-mont"));
+        assert!(prove.exists && prove.size > 0);
+        // Test params are not published, so the manifest check skips them
+        assert!(!prove.in_manifest && prove.sha256.is_empty());
+
+        let status = admin
+            .get_status(Request::new(GetStatusRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((status.in_flight, status.jobs), (0, 0));
+        assert!(!status.reloading);
+
+        let status = admin
+            .reload_params(Request::new(ReloadParamsRequest {}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert_eq!(error_info(&status).unwrap().reason, "RELOAD_REJECTED");
+
+        let old_params = encoder.params();
+        let admin = admin.with_reload(true);
+        admin
+            .reload_params(Request::new(ReloadParamsRequest {}))
+            .await
+            .unwrap();
+        assert!(!std::sync::Arc::ptr_eq(&old_params, &encoder.params()));
+    }
+}
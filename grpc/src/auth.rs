@@ -0,0 +1,306 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tonic::{metadata::MetadataMap, service::Interceptor, Request, Status};
+use tracing::Span;
+
+use crate::error::ServiceError;
+
+pub const CLIENT_ID_HEADER: &str = "x-zg-client";
+pub const TIMESTAMP_HEADER: &str = "x-zg-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-zg-signature";
+
+/// Accepted distance in seconds between a signed timestamp and the server
+/// clock.
+const MAX_CLOCK_SKEW: u64 = 300;
+
+#[derive(Clone, Default)]
+pub struct AuthConfig {
+    /// Secret of each client, by client id. Empty disables authentication.
+    pub keys: HashMap<String, String>,
+}
+
+impl fmt::Debug for AuthConfig {
+    // Lists the client ids only, the options are logged at startup
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("clients", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Client id of an authenticated request, stored in its extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity(pub String);
+
+/// Path of a gRPC request, `/<service>/<method>`, which `record_path` keeps
+/// in its extensions because interceptors do not see the URI.
+#[derive(Debug, Clone)]
+pub struct RequestPath(pub String);
+
+/// Interceptor accepting either `authorization: Bearer <secret>`, or the
+/// client id, a unix timestamp and
+/// `hex(HMAC-SHA256(secret, "<id>:<ts>:<path>"))` in the `x-zg-*` headers,
+/// where the path is the gRPC method or gateway route called.
+/// Signatures are only checked for calls that `record_path` saw.
+#[derive(Clone)]
+pub struct Authenticator {
+    keys: Arc<HashMap<String, String>>,
+}
+
+impl Authenticator {
+    pub fn new(config: &AuthConfig) -> Self {
+        Self {
+            keys: Arc::new(config.keys.clone()),
+        }
+    }
+
+    /// Checks the credentials of a call to `path`.
+    pub fn authenticate(
+        &self, path: &str, metadata: &MetadataMap,
+    ) -> Result<Option<ClientIdentity>, ServiceError> {
+        if self.keys.is_empty() {
+            return Ok(None);
+        }
+        let client = if let Some(token) = header(metadata, "authorization")? {
+            let token = token.strip_prefix("Bearer ").ok_or_else(|| {
+                ServiceError::Unauthenticated("Expected a bearer token".into())
+            })?;
+            self.check_token(token)?
+        } else if let Some(client) = header(metadata, CLIENT_ID_HEADER)? {
+            let timestamp = header(metadata, TIMESTAMP_HEADER)?;
+            let signature = header(metadata, SIGNATURE_HEADER)?;
+            match (timestamp, signature) {
+                (Some(timestamp), Some(signature)) => {
+                    self.check_signature(client, timestamp, path, signature)?
+                }
+                _ => {
+                    return Err(ServiceError::Unauthenticated(
+                        "Missing request signature".into(),
+                    ))
+                }
+            }
+        } else {
+            return Err(ServiceError::Unauthenticated(
+                "Missing credentials".into(),
+            ));
+        };
+        Ok(Some(ClientIdentity(client.to_string())))
+    }
+
+    fn check_token(&self, token: &str) -> Result<&str, ServiceError> {
+        // Compare MACs in constant time so the timing does not depend on the
+        // secrets
+        let tag = token_mac(token).finalize().into_bytes();
+        self.keys
+            .iter()
+            .find(|(_, secret)| token_mac(secret).verify_slice(&tag).is_ok())
+            .map(|(client, _)| client.as_str())
+            .ok_or_else(|| {
+                ServiceError::Unauthenticated("Unknown bearer token".into())
+            })
+    }
+
+    fn check_signature<'a>(
+        &self, client: &'a str, timestamp: &str, path: &str, signature: &str,
+    ) -> Result<&'a str, ServiceError> {
+        let unauthenticated =
+            |msg: &str| ServiceError::Unauthenticated(msg.to_string());
+        let secret = self
+            .keys
+            .get(client)
+            .ok_or_else(|| unauthenticated("Unknown client"))?;
+
+        let signed_at: u64 = timestamp
+            .parse()
+            .map_err(|_| unauthenticated("Malformed timestamp"))?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| ServiceError::Internal(e.to_string()))?
+            .as_secs();
+        if now.abs_diff(signed_at) > MAX_CLOCK_SKEW {
+            return Err(unauthenticated("Expired timestamp"));
+        }
+
+        let signature = hex::decode(signature)
+            .map_err(|_| unauthenticated("Malformed signature"))?;
+        signing_mac(secret, client, timestamp, path)
+            .verify_slice(&signature)
+            .map_err(|_| unauthenticated("Invalid signature"))?;
+        Ok(client)
+    }
+}
+
+impl Interceptor for Authenticator {
+    fn call(
+        &mut self, mut request: Request<()>,
+    ) -> Result<Request<()>, Status> {
+        let path = request
+            .extensions()
+            .get::<RequestPath>()
+            .map_or("", |path| path.0.as_str());
+        if let Some(identity) = self.authenticate(path, request.metadata())? {
+            request.extensions_mut().insert(identity);
+        }
+        Ok(request)
+    }
+}
+
+/// Records the authenticated client on the request span set up by
+/// `run_server`.
+pub fn record_client<T>(request: &Request<T>) {
+    if let Some(ClientIdentity(client)) = request.extensions().get() {
+        Span::current().record("client", client.as_str());
+    }
+}
+
+/// Keeps the path of a gRPC request for `Authenticator`, as a layer of the
+/// server in front of the intercepted services.
+pub fn record_path<B>(mut request: hyper::Request<B>) -> hyper::Request<B> {
+    let path = RequestPath(request.uri().path().to_string());
+    request.extensions_mut().insert(path);
+    request
+}
+
+/// Hex signature a client sends in `x-zg-signature` for a call to `path`,
+/// e.g. `/encoder.Encoder/EncodeBlob` or `/v1/encode`.
+pub fn sign_request(
+    secret: &str, client: &str, timestamp: u64, path: &str,
+) -> String {
+    let mac = signing_mac(secret, client, &timestamp.to_string(), path);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn signing_mac(
+    secret: &str, client: &str, timestamp: &str, path: &str,
+) -> Hmac<Sha256> {
+    let mut mac = token_mac(secret);
+    mac.update(format!("{client}:{timestamp}:{path}").as_bytes());
+    mac
+}
+
+fn token_mac(key: &str) -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(key.as_bytes())
+        .expect("HMAC accepts keys of any size")
+}
+
+fn header<'a>(
+    metadata: &'a MetadataMap, key: &str,
+) -> Result<Option<&'a str>, ServiceError> {
+    metadata
+        .get(key)
+        .map(|value| {
+            value.to_str().map_err(|_| {
+                ServiceError::Unauthenticated(format!("Malformed {key} header"))
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use tonic::{
+        metadata::MetadataMap, service::Interceptor, Code, Request, Status,
+    };
+
+    use super::{
+        record_path, sign_request, AuthConfig, Authenticator, ClientIdentity,
+        RequestPath, CLIENT_ID_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+    };
+
+    const PATH: &str = "/encoder.Encoder/EncodeBlob";
+
+    fn authenticate(
+        auth: &Authenticator, headers: &[(&'static str, String)],
+    ) -> Result<Option<ClientIdentity>, Status> {
+        let mut metadata = MetadataMap::new();
+        for (key, value) in headers {
+            metadata.insert(*key, value.parse().unwrap());
+        }
+        Ok(auth.authenticate(PATH, &metadata)?)
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let auth = Authenticator::new(&AuthConfig {
+            keys: [("disperser".to_string(), "secret".to_string())].into(),
+        });
+        let identity = Some(ClientIdentity("disperser".into()));
+
+        let bearer = |token: &str| {
+            authenticate(&auth, &[("authorization", format!("Bearer {token}"))])
+        };
+        assert_eq!(bearer("secret").unwrap(), identity);
+        assert_eq!(bearer("wrong").unwrap_err().code(), Code::Unauthenticated);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let signed = |timestamp: u64, secret: &str, path: &str| {
+            authenticate(
+                &auth,
+                &[
+                    (CLIENT_ID_HEADER, "disperser".into()),
+                    (TIMESTAMP_HEADER, timestamp.to_string()),
+                    (
+                        SIGNATURE_HEADER,
+                        sign_request(secret, "disperser", timestamp, path),
+                    ),
+                ],
+            )
+        };
+        assert_eq!(signed(now, "secret", PATH).unwrap(), identity);
+        assert!(signed(now, "wrong", PATH).is_err());
+        assert!(signed(now - 3600, "secret", PATH).is_err());
+        // A signature does not carry over to another method
+        assert!(signed(now, "secret", "/encoder.Signer/RecoverBlob").is_err());
+
+        assert_eq!(
+            authenticate(&auth, &[]).unwrap_err().code(),
+            Code::Unauthenticated
+        );
+        let open = Authenticator::new(&AuthConfig::default());
+        assert_eq!(authenticate(&open, &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_interceptor() {
+        let mut auth = Authenticator::new(&AuthConfig {
+            keys: [("disperser".to_string(), "secret".to_string())].into(),
+        });
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let call = |auth: &mut Authenticator, uri: &str| {
+            let http = record_path(hyper::Request::get(uri).body(()).unwrap());
+            let mut request = Request::new(());
+            let path = http.extensions().get::<RequestPath>().unwrap();
+            request.extensions_mut().insert(path.clone());
+            let metadata = request.metadata_mut();
+            metadata.insert(CLIENT_ID_HEADER, "disperser".parse().unwrap());
+            metadata.insert(TIMESTAMP_HEADER, now.to_string().parse().unwrap());
+            let signature = sign_request("secret", "disperser", now, PATH);
+            metadata.insert(SIGNATURE_HEADER, signature.parse().unwrap());
+            auth.call(request)
+        };
+
+        let request = call(&mut auth, PATH).unwrap();
+        assert_eq!(
+            request.extensions().get::<ClientIdentity>(),
+            Some(&ClientIdentity("disperser".into()))
+        );
+        let status =
+            call(&mut auth, "/encoder.Signer/VerifySlices").unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+}
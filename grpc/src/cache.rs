@@ -0,0 +1,175 @@
+use std::{
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use lru::LruCache;
+use prost::Message;
+use sha2::{Digest, Sha256};
+
+use crate::service::{encoder::EncodeBlobReply, ReplyOptions};
+
+#[derive(Debug, Clone, Default)]
+pub struct CacheConfig {
+    /// Memory budget for cached replies, counted in encoded reply bytes.
+    /// Zero disables the cache.
+    pub max_bytes: usize,
+}
+
+/// Identifies a reply by the payload digest and the options shaping it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    digest: [u8; 32],
+    options: ReplyOptions,
+}
+
+impl CacheKey {
+    pub fn new(data: &[u8], options: &ReplyOptions) -> Self {
+        Self {
+            digest: Sha256::digest(data).into(),
+            options: options.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+struct Entries {
+    lru: LruCache<CacheKey, EncodeBlobReply>,
+    bytes: usize,
+}
+
+/// LRU cache of encode replies, so a retried request returns the same reply
+/// without running the AMT proofs again.
+pub struct ReplyCache {
+    entries: Mutex<Entries>,
+    max_bytes: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ReplyCache {
+    pub fn new(config: &CacheConfig) -> Option<Self> {
+        if config.max_bytes == 0 {
+            return None;
+        }
+        Some(Self {
+            entries: Mutex::new(Entries {
+                lru: LruCache::unbounded(),
+                bytes: 0,
+            }),
+            max_bytes: config.max_bytes,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        })
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<EncodeBlobReply> {
+        let reply = self.entries.lock().unwrap().lru.get(key).cloned();
+        let counter = if reply.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        reply
+    }
+
+    pub fn insert(&self, key: CacheKey, reply: EncodeBlobReply) {
+        let size = reply.encoded_len();
+        if size > self.max_bytes {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(old) = entries.lru.put(key, reply) {
+            entries.bytes -= old.encoded_len();
+        }
+        entries.bytes += size;
+        while entries.bytes > self.max_bytes {
+            let Some((_, evicted)) = entries.lru.pop_lru() else {
+                break;
+            };
+            entries.bytes -= evicted.encoded_len();
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.lru.clear();
+        entries.bytes = 0;
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries.lock().unwrap();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: entries.lru.len(),
+            bytes: entries.bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheConfig, CacheKey, CacheStats, ReplyCache};
+    use crate::service::{encoder::EncodeBlobReply, ReplyOptions};
+    use prost::Message;
+
+    fn reply(storage_root: u8) -> EncodeBlobReply {
+        EncodeBlobReply {
+            storage_root: vec![storage_root; 32],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let size = reply(0).encoded_len();
+        let cache = ReplyCache::new(&CacheConfig {
+            max_bytes: 2 * size,
+        })
+        .unwrap();
+        let options = ReplyOptions::default();
+        let keys: Vec<_> =
+            (0..3u8).map(|i| CacheKey::new(&[i], &options)).collect();
+
+        cache.insert(keys[0].clone(), reply(0));
+        cache.insert(keys[1].clone(), reply(1));
+        assert_eq!(cache.get(&keys[0]), Some(reply(0)));
+        // keys[1] is now the least recently used entry
+        cache.insert(keys[2].clone(), reply(2));
+
+        assert_eq!(cache.get(&keys[1]), None);
+        assert_eq!(cache.get(&keys[2]), Some(reply(2)));
+        let light_options = ReplyOptions {
+            light_slice: true,
+            ..Default::default()
+        };
+        assert_eq!(cache.get(&CacheKey::new(&[0], &light_options)), None);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 2,
+                entries: 2,
+                bytes: 2 * size,
+            }
+        );
+    }
+
+    #[test]
+    fn test_disabled() {
+        assert!(ReplyCache::new(&CacheConfig { max_bytes: 0 }).is_none());
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use tonic::{metadata::MetadataMap, Request};
+use tracing::info;
+
+use crate::{error::ServiceError, metrics};
+
+const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// Tonic drops a handler once `grpc-timeout` has passed since the request
+/// arrived, which is a little before the deadline seen by the handler.
+const DEADLINE_SLACK: Duration = Duration::from_millis(100);
+
+const LIVE: u8 = 0;
+const DISCONNECTED: u8 = 1;
+const DEADLINE_EXCEEDED: u8 = 2;
+
+/// Why an encode stopped before it finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelReason {
+    /// The gRPC deadline of the call expired.
+    DeadlineExceeded,
+    /// The client went away before the reply was ready.
+    Disconnected,
+}
+
+impl CancelReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CancelReason::DeadlineExceeded => "deadline_exceeded",
+            CancelReason::Disconnected => "disconnected",
+        }
+    }
+}
+
+/// Checked by an encode between its stages, so it stops once nobody waits
+/// for the result. A default token is never cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    deadline: Option<Instant>,
+    state: Arc<AtomicU8>,
+    reported: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Takes the deadline from the `grpc-timeout` header of `request`.
+    pub fn from_request<T>(request: &Request<T>) -> Self {
+        Self {
+            deadline: grpc_timeout(request.metadata())
+                .map(|timeout| Instant::now() + timeout),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn cancel(&self, reason: CancelReason) {
+        let state = match reason {
+            CancelReason::DeadlineExceeded => DEADLINE_EXCEEDED,
+            CancelReason::Disconnected => DISCONNECTED,
+        };
+        let _ = self.state.compare_exchange(
+            LIVE,
+            state,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Cancels the token when dropped. Held by a handler that awaits the
+    /// encode, which tonic drops when the client disconnects or the deadline
+    /// expires.
+    pub fn cancel_on_drop(&self) -> CancelOnDrop { CancelOnDrop(self.clone()) }
+
+    pub fn reason(&self) -> Option<CancelReason> {
+        match self.state.load(Ordering::Relaxed) {
+            DEADLINE_EXCEEDED => Some(CancelReason::DeadlineExceeded),
+            DISCONNECTED => Some(CancelReason::Disconnected),
+            _ if self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline) =>
+            {
+                Some(CancelReason::DeadlineExceeded)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool { self.reason().is_some() }
+
+    /// Fails with `ServiceError::Cancelled` once cancelled. The first failure
+    /// is logged and counted.
+    pub fn check(&self) -> Result<(), ServiceError> {
+        match self.reason() {
+            Some(reason) => Err(self.cancelled(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// The error of an encode that gave up on this token.
+    pub fn error(&self) -> ServiceError {
+        self.cancelled(self.reason().unwrap_or(CancelReason::Disconnected))
+    }
+
+    fn cancelled(&self, reason: CancelReason) -> ServiceError {
+        if !self.reported.swap(true, Ordering::Relaxed) {
+            info!(reason = reason.as_str(), "Encode cancelled");
+            metrics::record_cancelled(reason);
+        }
+        ServiceError::Cancelled(reason)
+    }
+}
+
+/// See `CancelToken::cancel_on_drop`.
+pub struct CancelOnDrop(CancelToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let token = &self.0;
+        let reason = match token.deadline {
+            Some(deadline) if Instant::now() + DEADLINE_SLACK >= deadline => {
+                CancelReason::DeadlineExceeded
+            }
+            _ => CancelReason::Disconnected,
+        };
+        token.cancel(reason);
+    }
+}
+
+/// Parses `grpc-timeout`: at most 8 digits and a unit out of `HMSmun`.
+fn grpc_timeout(metadata: &MetadataMap) -> Option<Duration> {
+    let value = metadata.get(GRPC_TIMEOUT_HEADER)?.to_str().ok()?;
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (amount, unit) = value.split_at(value.len() - 1);
+    let amount: u64 = amount.parse().ok()?;
+    let timeout = match unit {
+        "H" => Duration::from_secs(amount * 60 * 60),
+        "M" => Duration::from_secs(amount * 60),
+        "S" => Duration::from_secs(amount),
+        "m" => Duration::from_millis(amount),
+        "u" => Duration::from_micros(amount),
+        "n" => Duration::from_nanos(amount),
+        _ => return None,
+    };
+    Some(timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use tonic::Request;
+
+    use super::{CancelReason, CancelToken};
+    use crate::ServiceError;
+
+    #[test]
+    fn test_cancel_token() {
+        let token = CancelToken::default();
+        assert!(token.check().is_ok());
+        drop(token.cancel_on_drop());
+        assert!(matches!(
+            token.check(),
+            Err(ServiceError::Cancelled(CancelReason::Disconnected))
+        ));
+
+        let token = CancelToken::default().with_deadline(Instant::now());
+        assert_eq!(token.reason(), Some(CancelReason::DeadlineExceeded));
+        // Dropped by tonic at the deadline
+        let token = CancelToken::default()
+            .with_deadline(Instant::now() + Duration::from_millis(10));
+        drop(token.cancel_on_drop());
+        assert_eq!(token.reason(), Some(CancelReason::DeadlineExceeded));
+
+        let mut request = Request::new(());
+        request.set_timeout(Duration::from_secs(60));
+        let token = CancelToken::from_request(&request);
+        assert!(!token.is_cancelled());
+        assert!(token.deadline.unwrap() > Instant::now());
+        request
+            .metadata_mut()
+            .insert("grpc-timeout", "1x".parse().unwrap());
+        assert_eq!(CancelToken::from_request(&request).deadline, None);
+    }
+}
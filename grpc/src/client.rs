@@ -0,0 +1,189 @@
+use std::{fmt, sync::Arc};
+
+use amt::ec_algebra::{CanonicalDeserialize, CurveGroup};
+use ark_bn254::G1Affine;
+use tonic::{
+    transport::{Channel, Endpoint},
+    Status,
+};
+use zg_encoder::{
+    constants::BLOB_ROW_ENCODED, CommitmentFormat, EncodedSlice, VerifierError,
+    ZgSignerParams,
+};
+
+use crate::{
+    service::{
+        check_slices, deserialize_commitment,
+        encoder::{encoder_client, EncodeBlobReply, EncodeBlobRequest},
+    },
+    MESSAGE_SIZE_LIMIT,
+};
+
+/// `EncodeBlobReply` with every field decoded.
+#[derive(Debug, PartialEq)]
+pub struct EncodedReply {
+    pub erasure_commitment: G1Affine,
+    pub storage_root: [u8; 32],
+    pub encoded_slice: Vec<EncodedSlice>,
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    Transport(tonic::transport::Error),
+    Status(Status),
+    MalformedReply(String),
+    InvalidSlice { index: usize, error: VerifierError },
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Transport(e) => write!(f, "Transport error: {}", e),
+            ClientError::Status(status) => write!(
+                f,
+                "Encoder returned {:?}: {}",
+                status.code(),
+                status.message()
+            ),
+            ClientError::MalformedReply(e) => {
+                write!(f, "Malformed reply: {}", e)
+            }
+            ClientError::InvalidSlice { index, error } => {
+                write!(f, "Slice {} failed verification: {}", index, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Transport(e) => Some(e),
+            ClientError::Status(status) => Some(status),
+            ClientError::InvalidSlice { error, .. } => Some(error),
+            ClientError::MalformedReply(_) => None,
+        }
+    }
+}
+
+impl From<tonic::transport::Error> for ClientError {
+    fn from(error: tonic::transport::Error) -> Self {
+        ClientError::Transport(error)
+    }
+}
+
+impl From<Status> for ClientError {
+    fn from(status: Status) -> Self { ClientError::Status(status) }
+}
+
+/// Encoder client returning decoded replies, and verifying them when signer
+/// params are set.
+#[derive(Clone)]
+pub struct EncoderClient {
+    inner: encoder_client::EncoderClient<Channel>,
+    verifier: Option<Arc<ZgSignerParams>>,
+}
+
+impl EncoderClient {
+    pub async fn connect(
+        dst: impl TryInto<Endpoint, Error = impl Into<tonic::codegen::StdError>>,
+    ) -> Result<Self, ClientError> {
+        let channel = Endpoint::new(dst)?.connect().await?;
+        Ok(Self::new(channel))
+    }
+
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            inner: encoder_client::EncoderClient::new(channel)
+                .max_decoding_message_size(MESSAGE_SIZE_LIMIT)
+                .max_encoding_message_size(MESSAGE_SIZE_LIMIT),
+            verifier: None,
+        }
+    }
+
+    /// Verifies every slice of each reply against its commitment and root.
+    pub fn with_verifier(mut self, params: Arc<ZgSignerParams>) -> Self {
+        self.verifier = Some(params);
+        self
+    }
+
+    pub async fn encode_blob(
+        &mut self, data: &[u8],
+    ) -> Result<EncodedReply, ClientError> {
+        let request = EncodeBlobRequest {
+            data: data.to_vec(),
+            ..Default::default()
+        };
+        let reply = self.inner.encode_blob(request).await?.into_inner();
+        let reply = decode_reply(reply)?;
+        if let Some(params) = &self.verifier {
+            verify_reply(params, &reply)?;
+        }
+        Ok(reply)
+    }
+}
+
+/// Decodes a reply carrying all rows as full slices.
+pub fn decode_reply(
+    reply: EncodeBlobReply,
+) -> Result<EncodedReply, ClientError> {
+    let erasure_commitment = deserialize_commitment(
+        &reply.erasure_commitment,
+        CommitmentFormat::ArkUncompressed,
+    )
+    .map_err(ClientError::MalformedReply)?
+    .into_affine();
+    let storage_root: [u8; 32] =
+        reply.storage_root.as_slice().try_into().map_err(|_| {
+            ClientError::MalformedReply("Storage root must be 32 bytes".into())
+        })?;
+    if reply.encoded_slice.len() != BLOB_ROW_ENCODED {
+        return Err(ClientError::MalformedReply(format!(
+            "Expected {} slices, got {}",
+            BLOB_ROW_ENCODED,
+            reply.encoded_slice.len()
+        )));
+    }
+    let encoded_slice = reply
+        .encoded_slice
+        .iter()
+        .enumerate()
+        .map(|(index, raw)| {
+            let slice = EncodedSlice::deserialize_uncompressed(&raw[..])
+                .map_err(|e| {
+                    ClientError::MalformedReply(format!("Slice {index}: {e}"))
+                })?;
+            if slice.index != index {
+                return Err(ClientError::MalformedReply(format!(
+                    "Slice {index} has row index {}",
+                    slice.index
+                )));
+            }
+            Ok(slice)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(EncodedReply {
+        erasure_commitment,
+        storage_root,
+        encoded_slice,
+    })
+}
+
+pub fn verify_reply(
+    params: &ZgSignerParams, reply: &EncodedReply,
+) -> Result<(), ClientError> {
+    let slices: Vec<_> = reply.encoded_slice.iter().map(Some).collect();
+    let errors = check_slices(
+        params,
+        &reply.erasure_commitment.into(),
+        &reply.storage_root,
+        &slices,
+    );
+    match errors.into_iter().enumerate().find_map(|(index, error)| {
+        error.map(|error| ClientError::InvalidSlice { index, error })
+    }) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,429 @@
+use std::{collections::HashMap, fmt};
+
+use amt::AmtProofError;
+use prost::Message;
+use tonic::{Code, Status};
+use zg_encoder::{AmtError, EncoderError, MerkleError, VerifierError};
+
+use crate::{
+    cancel::CancelReason,
+    service::encoder::{
+        self, amt_error, merkle_error, verifier_error, BlobError,
+        IncorrectAmtProof, SizeMismatch, UnmatchedCosetIndex, UnmatchedIndex,
+    },
+};
+
+const ERROR_DOMAIN: &str = "zg-encoder";
+const ERROR_INFO_TYPE_URL: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+
+/// Failures of the encoder RPCs, mapped to gRPC codes with a
+/// `google.rpc.ErrorInfo` detail so clients can branch on `reason`.
+#[derive(Debug)]
+pub enum ServiceError {
+    Encoder(EncoderError),
+    InvalidRowIndex { index: usize, expected_max: usize },
+    UnsupportedOption(&'static str),
+    MalformedCommitment(String),
+    MalformedStorageRoot { actual: usize },
+    RecoveryFailed { error: String, dropped_rows: usize },
+    Params(String),
+    Unauthenticated(String),
+    JobNotFound(String),
+    ReloadRejected(String),
+    ReloadInProgress,
+    QueueFull { queue_size: usize },
+    TooManyJobs { max_jobs: usize },
+    Cancelled(CancelReason),
+    Internal(String),
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::Encoder(e) => write!(f, "{}", e),
+            ServiceError::InvalidRowIndex {
+                index,
+                expected_max,
+            } => write!(
+                f,
+                "Row index {} is out of range, expected less than {}",
+                index, expected_max
+            ),
+            ServiceError::UnsupportedOption(option) => {
+                write!(f, "Option {} is not supported by this call", option)
+            }
+            ServiceError::MalformedCommitment(e) => {
+                write!(f, "Malformed erasure commitment: {}", e)
+            }
+            ServiceError::MalformedStorageRoot { actual } => {
+                write!(f, "Storage root is {} bytes, expected 32", actual)
+            }
+            ServiceError::RecoveryFailed {
+                error,
+                dropped_rows,
+            } => write!(f, "{} ({} rows dropped)", error, dropped_rows),
+            ServiceError::Params(e) => write!(f, "Params unavailable: {}", e),
+            ServiceError::Unauthenticated(e) => {
+                write!(f, "Unauthenticated: {}", e)
+            }
+            ServiceError::JobNotFound(id) => {
+                write!(f, "Unknown or expired job {}", id)
+            }
+            ServiceError::ReloadRejected(e) => {
+                write!(f, "Params reload rejected: {}", e)
+            }
+            ServiceError::ReloadInProgress => {
+                write!(f, "A params reload is already running")
+            }
+            ServiceError::QueueFull { queue_size } => write!(
+                f,
+                "Encoder queue is full, {} requests are waiting",
+                queue_size
+            ),
+            ServiceError::TooManyJobs { max_jobs } => {
+                write!(
+                    f,
+                    "{} encode jobs are already queued or running",
+                    max_jobs
+                )
+            }
+            ServiceError::Cancelled(CancelReason::DeadlineExceeded) => {
+                write!(f, "Deadline expired before encoding finished")
+            }
+            ServiceError::Cancelled(CancelReason::Disconnected) => {
+                write!(f, "Client went away before encoding finished")
+            }
+            ServiceError::Internal(e) => write!(f, "Internal error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ServiceError::Encoder(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<EncoderError> for ServiceError {
+    fn from(error: EncoderError) -> Self { ServiceError::Encoder(error) }
+}
+
+impl From<ServiceError> for Status {
+    fn from(error: ServiceError) -> Self {
+        let message = error.to_string();
+        match error {
+            ServiceError::Encoder(EncoderError::TooLargeBlob {
+                actual,
+                expected_max,
+            }) => status_with_info(
+                Code::InvalidArgument,
+                message,
+                "TOO_LARGE_BLOB",
+                [("actual", actual), ("expected_max", expected_max)],
+            ),
+            ServiceError::InvalidRowIndex {
+                index,
+                expected_max,
+            } => status_with_info(
+                Code::InvalidArgument,
+                message,
+                "INVALID_ROW_INDEX",
+                [("index", index), ("expected_max", expected_max)],
+            ),
+            ServiceError::UnsupportedOption(_) => status_with_info(
+                Code::InvalidArgument,
+                message,
+                "UNSUPPORTED_OPTION",
+                [],
+            ),
+            ServiceError::MalformedCommitment(_) => status_with_info(
+                Code::InvalidArgument,
+                message,
+                "MALFORMED_COMMITMENT",
+                [],
+            ),
+            ServiceError::MalformedStorageRoot { actual } => status_with_info(
+                Code::InvalidArgument,
+                message,
+                "MALFORMED_STORAGE_ROOT",
+                [("actual", actual), ("expected", 32)],
+            ),
+            ServiceError::RecoveryFailed { dropped_rows, .. } => {
+                status_with_info(
+                    Code::InvalidArgument,
+                    message,
+                    "RECOVERY_FAILED",
+                    [("dropped_rows", dropped_rows)],
+                )
+            }
+            ServiceError::Params(_) => status_with_info(
+                Code::FailedPrecondition,
+                message,
+                "PARAMS_UNAVAILABLE",
+                [],
+            ),
+            ServiceError::Unauthenticated(_) => status_with_info(
+                Code::Unauthenticated,
+                message,
+                "UNAUTHENTICATED",
+                [],
+            ),
+            ServiceError::JobNotFound(_) => {
+                status_with_info(Code::NotFound, message, "JOB_NOT_FOUND", [])
+            }
+            ServiceError::ReloadRejected(_) => status_with_info(
+                Code::FailedPrecondition,
+                message,
+                "RELOAD_REJECTED",
+                [],
+            ),
+            ServiceError::ReloadInProgress => status_with_info(
+                Code::Aborted,
+                message,
+                "RELOAD_IN_PROGRESS",
+                [],
+            ),
+            ServiceError::QueueFull { queue_size } => status_with_info(
+                Code::ResourceExhausted,
+                message,
+                "QUEUE_FULL",
+                [("queue_size", queue_size)],
+            ),
+            ServiceError::TooManyJobs { max_jobs } => status_with_info(
+                Code::ResourceExhausted,
+                message,
+                "TOO_MANY_JOBS",
+                [("max_jobs", max_jobs)],
+            ),
+            ServiceError::Encoder(EncoderError::Cancelled)
+            | ServiceError::Cancelled(CancelReason::Disconnected) => {
+                status_with_info(Code::Cancelled, message, "CANCELLED", [])
+            }
+            ServiceError::Cancelled(CancelReason::DeadlineExceeded) => {
+                status_with_info(
+                    Code::DeadlineExceeded,
+                    message,
+                    "DEADLINE_EXCEEDED",
+                    [],
+                )
+            }
+            ServiceError::Internal(_) => {
+                status_with_info(Code::Internal, message, "INTERNAL", [])
+            }
+        }
+    }
+}
+
+/// `google.rpc.ErrorInfo`
+#[derive(Clone, PartialEq, Message)]
+pub struct ErrorInfo {
+    #[prost(string, tag = "1")]
+    pub reason: String,
+    #[prost(string, tag = "2")]
+    pub domain: String,
+    #[prost(map = "string, string", tag = "3")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// `google.rpc.Status`, the payload of the `grpc-status-details-bin` trailer
+#[derive(Clone, PartialEq, Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<RpcAny>,
+}
+
+/// `google.protobuf.Any`
+#[derive(Clone, PartialEq, Message)]
+struct RpcAny {
+    #[prost(string, tag = "1")]
+    type_url: String,
+    #[prost(bytes = "vec", tag = "2")]
+    value: Vec<u8>,
+}
+
+fn status_with_info<const N: usize>(
+    code: Code, message: String, reason: &str, metadata: [(&str, usize); N],
+) -> Status {
+    let info = ErrorInfo {
+        reason: reason.to_string(),
+        domain: ERROR_DOMAIN.to_string(),
+        metadata: metadata
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    };
+    let details = RpcStatus {
+        code: code as i32,
+        message: message.clone(),
+        details: vec![RpcAny {
+            type_url: ERROR_INFO_TYPE_URL.to_string(),
+            value: info.encode_to_vec(),
+        }],
+    };
+    Status::with_details(code, message, details.encode_to_vec().into())
+}
+
+/// Reads the `ErrorInfo` attached by the server, if any.
+pub fn error_info(status: &Status) -> Option<ErrorInfo> {
+    let details = RpcStatus::decode(status.details()).ok()?;
+    details
+        .details
+        .into_iter()
+        .find(|any| any.type_url == ERROR_INFO_TYPE_URL)
+        .and_then(|any| ErrorInfo::decode(&any.value[..]).ok())
+}
+
+impl From<ServiceError> for BlobError {
+    fn from(error: ServiceError) -> Self {
+        let status = Status::from(error);
+        let info = error_info(&status);
+        Self {
+            code: status.code() as i32,
+            message: status.message().to_string(),
+            reason: info
+                .as_ref()
+                .map(|info| info.reason.clone())
+                .unwrap_or_default(),
+            metadata: info.map(|info| info.metadata).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<VerifierError> for encoder::VerifierError {
+    fn from(error: VerifierError) -> Self {
+        use verifier_error::Error;
+        let error = match error {
+            VerifierError::AMT(e) => Error::Amt(e.into()),
+            VerifierError::Merkle(e) => Error::Merkle(e.into()),
+            VerifierError::UnmatchedAMTIndex {
+                row_index,
+                amt_index,
+            } => Error::UnmatchedAmtIndex(UnmatchedIndex {
+                row_index: row_index as u64,
+                index: amt_index as u64,
+            }),
+            VerifierError::UnmatchedMerkleIndex {
+                row_index,
+                merkle_index,
+            } => Error::UnmatchedMerkleIndex(UnmatchedIndex {
+                row_index: row_index as u64,
+                index: merkle_index as u64,
+            }),
+        };
+        Self { error: Some(error) }
+    }
+}
+
+impl From<AmtError> for encoder::AmtError {
+    fn from(error: AmtError) -> Self {
+        use amt_error::Error;
+        let error = match error {
+            AmtError::IncorrectCommitment => Error::IncorrectCommitment(true),
+            AmtError::IncorrectRowSize { actual, expected } => {
+                Error::IncorrectRowSize(size_mismatch(actual, expected))
+            }
+            AmtError::RowIndexOverflow {
+                actual,
+                expected_max,
+            } => Error::RowIndexOverflow(size_mismatch(actual, expected_max)),
+            AmtError::UnmatchedCosetIndex {
+                coset_index,
+                local_index,
+                amt_index,
+            } => Error::UnmatchedCosetIndex(UnmatchedCosetIndex {
+                coset_index: coset_index as u64,
+                local_index: local_index as u64,
+                amt_index: amt_index as u64,
+            }),
+            AmtError::IncorrectProof {
+                coset_index,
+                amt_index,
+                error,
+            } => {
+                let (error, kzg_index) = match error {
+                    AmtProofError::UnexpectedDataLength => {
+                        (encoder::AmtProofError::UnexpectedDataLength, 0)
+                    }
+                    AmtProofError::IncorrectPosition => {
+                        (encoder::AmtProofError::IncorrectPosition, 0)
+                    }
+                    AmtProofError::KzgError(index) => {
+                        (encoder::AmtProofError::KzgError, index)
+                    }
+                    AmtProofError::InconsistentCommitment => {
+                        (encoder::AmtProofError::InconsistentCommitment, 0)
+                    }
+                    AmtProofError::FailedLowDegreeTest => {
+                        (encoder::AmtProofError::FailedLowDegreeTest, 0)
+                    }
+                };
+                Error::IncorrectProof(IncorrectAmtProof {
+                    coset_index: coset_index as u64,
+                    amt_index: amt_index as u64,
+                    error: error.into(),
+                    kzg_index: kzg_index as u64,
+                })
+            }
+        };
+        Self { error: Some(error) }
+    }
+}
+
+impl From<MerkleError> for encoder::MerkleError {
+    fn from(error: MerkleError) -> Self {
+        use merkle_error::Error;
+        let error = match error {
+            MerkleError::IncorrectRoot => Error::IncorrectRoot(true),
+            MerkleError::IncorrectSize { actual, expected } => {
+                Error::IncorrectSize(size_mismatch(actual, expected))
+            }
+            MerkleError::RowIndexOverflow {
+                actual,
+                expected_max,
+            } => Error::RowIndexOverflow(size_mismatch(actual, expected_max)),
+            MerkleError::IncorrectLocalRoot { row_index } => {
+                Error::IncorrectLocalRoot(row_index as u64)
+            }
+            MerkleError::IncorrectProof { row_index } => {
+                Error::IncorrectProof(row_index as u64)
+            }
+        };
+        Self { error: Some(error) }
+    }
+}
+
+fn size_mismatch(actual: usize, expected: usize) -> SizeMismatch {
+    SizeMismatch {
+        actual: actual as u64,
+        expected: expected as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{error_info, ServiceError};
+    use tonic::{Code, Status};
+    use zg_encoder::EncoderError;
+
+    #[test]
+    fn test_too_large_blob_status() {
+        let status: Status = ServiceError::from(EncoderError::TooLargeBlob {
+            actual: 20,
+            expected_max: 10,
+        })
+        .into();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let info = error_info(&status).unwrap();
+        assert_eq!(info.reason, "TOO_LARGE_BLOB");
+        assert_eq!(info.metadata["actual"], "20");
+        assert_eq!(info.metadata["expected_max"], "10");
+    }
+}
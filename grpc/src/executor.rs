@@ -0,0 +1,187 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tonic::Status;
+use tracing::Span;
+
+use crate::error::ServiceError;
+
+#[derive(Debug, Clone)]
+pub struct ExecutorConfig {
+    /// Encodes running at the same time. Each encode already spreads over all
+    /// cores (or the GPU), so this is kept small.
+    pub max_concurrency: usize,
+    /// Encodes allowed to wait for a free slot before new ones are rejected.
+    pub queue_size: usize,
+}
+
+impl Default for ExecutorConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 1,
+            queue_size: 64,
+        }
+    }
+}
+
+/// Runs CPU-heavy jobs on the blocking thread pool, at most
+/// `max_concurrency` at a time and with a bounded number of waiting jobs.
+#[derive(Clone)]
+pub struct Executor {
+    slots: Arc<Semaphore>,
+    queued: Arc<AtomicUsize>,
+    queue_size: usize,
+    max_concurrency: usize,
+}
+
+impl Executor {
+    pub fn new(config: &ExecutorConfig) -> Self {
+        let max_concurrency = config.max_concurrency.max(1);
+        Self {
+            slots: Arc::new(Semaphore::new(max_concurrency)),
+            queued: Arc::new(AtomicUsize::new(0)),
+            queue_size: config.queue_size,
+            max_concurrency,
+        }
+    }
+
+    /// Number of jobs waiting for a free slot.
+    pub fn queue_depth(&self) -> usize { self.queued.load(Ordering::Relaxed) }
+
+    /// Number of jobs running or waiting for a slot.
+    pub fn in_flight(&self) -> usize {
+        self.queue_depth() + self.max_concurrency
+            - self.slots.available_permits()
+    }
+
+    /// Takes a free slot, or a place in the queue to wait for one. Fails with
+    /// `QueueFull` when the queue is full, so callers can reject a job before
+    /// waiting for it to start.
+    pub fn reserve(&self) -> Result<Reservation, ServiceError> {
+        if let Ok(permit) = self.slots.clone().try_acquire_owned() {
+            return Ok(Reservation {
+                slots: self.slots.clone(),
+                permit: Some(permit),
+                _queued: None,
+            });
+        }
+
+        if self.queued.fetch_add(1, Ordering::Relaxed) >= self.queue_size {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            return Err(ServiceError::QueueFull {
+                queue_size: self.queue_size,
+            });
+        }
+        Ok(Reservation {
+            slots: self.slots.clone(),
+            permit: None,
+            // Leaves the queue even if the request is cancelled while waiting
+            _queued: Some(QueueGuard(self.queued.clone())),
+        })
+    }
+
+    /// Waits for a free slot, or fails with `RESOURCE_EXHAUSTED` when the
+    /// queue is full. The job holding the permit owns the slot until dropped.
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, ServiceError> {
+        self.reserve()?.acquire().await
+    }
+
+    pub async fn run<F, T>(&self, job: F) -> Result<T, Status>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.reserve()?.run(job).await
+    }
+}
+
+/// A slot of an `Executor`, or a place in its queue.
+pub struct Reservation {
+    slots: Arc<Semaphore>,
+    permit: Option<OwnedSemaphorePermit>,
+    _queued: Option<QueueGuard>,
+}
+
+impl Reservation {
+    /// Waits for the slot, leaving the queue once it is free.
+    pub async fn acquire(self) -> Result<OwnedSemaphorePermit, ServiceError> {
+        if let Some(permit) = self.permit {
+            return Ok(permit);
+        }
+        self.slots
+            .acquire_owned()
+            .await
+            .map_err(|e| ServiceError::Internal(e.to_string()))
+    }
+
+    pub async fn run<F, T>(self, job: F) -> Result<T, Status>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = self.acquire().await?;
+        let span = Span::current();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            span.in_scope(job)
+        })
+        .await
+        .map_err(|e| ServiceError::Internal(e.to_string()).into())
+    }
+}
+
+struct QueueGuard(Arc<AtomicUsize>);
+
+impl Drop for QueueGuard {
+    fn drop(&mut self) { self.0.fetch_sub(1, Ordering::Relaxed); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Executor, ExecutorConfig};
+    use crate::{error_info, ServiceError};
+    use tonic::Code;
+
+    #[tokio::test]
+    async fn test_queue_full() {
+        let executor = Executor::new(&ExecutorConfig {
+            max_concurrency: 1,
+            queue_size: 1,
+        });
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+
+        let running = tokio::spawn({
+            let executor = executor.clone();
+            async move { executor.run(move || rx.recv().unwrap()).await }
+        });
+        while executor.slots.available_permits() > 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let queued = tokio::spawn({
+            let executor = executor.clone();
+            async move { executor.run(|| ()).await }
+        });
+        while executor.queue_depth() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        assert_eq!(executor.in_flight(), 2);
+        assert!(matches!(
+            executor.reserve(),
+            Err(ServiceError::QueueFull { queue_size: 1 })
+        ));
+        let rejected = executor.run(|| ()).await.unwrap_err();
+        assert_eq!(rejected.code(), Code::ResourceExhausted);
+        assert_eq!(error_info(&rejected).unwrap().reason, "QUEUE_FULL");
+
+        tx.send(()).unwrap();
+        running.await.unwrap().unwrap();
+        queued.await.unwrap().unwrap();
+        assert_eq!(executor.queue_depth(), 0);
+        assert_eq!(executor.in_flight(), 0);
+    }
+}
@@ -0,0 +1,371 @@
+use std::{collections::HashMap, future::Future, net::SocketAddr};
+
+use axum::{
+    extract::{rejection::JsonRejection, DefaultBodyLimit, State},
+    http::{HeaderMap, StatusCode, Uri},
+    middleware::from_fn,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use tonic::{metadata::MetadataMap, Code, Request, Status};
+
+use crate::{
+    auth::{Authenticator, ClientIdentity},
+    error::error_info,
+    metrics,
+    service::encoder::{
+        encoder_server::Encoder, signer_server::Signer, CommitmentFormat,
+        DroppedRow, EncodeBlobRequest, RecoverBlobRequest, SliceResult,
+        VerifySlicesRequest,
+    },
+    EncoderService, SignerService,
+};
+
+/// How byte fields are written in the JSON bodies. Replies use the encoding
+/// of their request.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ByteEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+impl ByteEncoding {
+    fn decode(self, field: &str, value: &str) -> Result<Vec<u8>, Status> {
+        let decoded = match self {
+            ByteEncoding::Hex => {
+                let value = value.strip_prefix("0x").unwrap_or(value);
+                hex::decode(value).map_err(|e| e.to_string())
+            }
+            ByteEncoding::Base64 => {
+                STANDARD.decode(value).map_err(|e| e.to_string())
+            }
+        };
+        decoded.map_err(|e| {
+            Status::invalid_argument(format!("Malformed {field}: {e}"))
+        })
+    }
+
+    fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            ByteEncoding::Hex => hex::encode(bytes),
+            ByteEncoding::Base64 => STANDARD.encode(bytes),
+        }
+    }
+}
+
+/// Layout of `erasure_commitment`, named as in `CommitmentFormat` of the
+/// proto file.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CommitmentFormatName {
+    #[default]
+    ArkUncompressed,
+    ArkCompressed,
+    EvmBigEndian,
+}
+
+impl From<CommitmentFormatName> for i32 {
+    fn from(format: CommitmentFormatName) -> Self {
+        let format = match format {
+            CommitmentFormatName::ArkUncompressed => {
+                CommitmentFormat::ArkUncompressed
+            }
+            CommitmentFormatName::ArkCompressed => {
+                CommitmentFormat::ArkCompressed
+            }
+            CommitmentFormatName::EvmBigEndian => {
+                CommitmentFormat::EvmBigEndian
+            }
+        };
+        format.into()
+    }
+}
+
+#[derive(Deserialize)]
+struct EncodeBody {
+    data: String,
+    #[serde(default)]
+    encoding: ByteEncoding,
+    #[serde(default)]
+    require_data: bool,
+    #[serde(default)]
+    light_slice: bool,
+    #[serde(default)]
+    row_indices: Vec<u32>,
+    #[serde(default)]
+    commitment_format: CommitmentFormatName,
+}
+
+#[derive(Serialize)]
+struct EncodeResponse {
+    version: u32,
+    erasure_commitment: String,
+    storage_root: String,
+    encoded_data: String,
+    encoded_slice: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct VerifyBody {
+    erasure_commitment: String,
+    storage_root: String,
+    encoded_slice: Vec<String>,
+    #[serde(default)]
+    encoding: ByteEncoding,
+    #[serde(default)]
+    commitment_format: CommitmentFormatName,
+}
+
+#[derive(Serialize)]
+struct VerifyResponse {
+    results: Vec<SliceResult>,
+}
+
+#[derive(Deserialize)]
+struct RecoverBody {
+    rows: HashMap<u32, String>,
+    #[serde(default)]
+    verify: bool,
+    #[serde(default)]
+    erasure_commitment: String,
+    #[serde(default)]
+    storage_root: String,
+    #[serde(default)]
+    encoding: ByteEncoding,
+    #[serde(default)]
+    commitment_format: CommitmentFormatName,
+}
+
+#[derive(Serialize)]
+struct RecoverResponse {
+    data: String,
+    dropped_rows: Vec<DroppedRow>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: i32,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    metadata: HashMap<String, String>,
+}
+
+/// Wraps a gRPC status so handlers can return it with `?`.
+pub struct GatewayError(Status);
+
+impl From<Status> for GatewayError {
+    fn from(status: Status) -> Self { GatewayError(status) }
+}
+
+impl From<JsonRejection> for GatewayError {
+    fn from(rejection: JsonRejection) -> Self {
+        GatewayError(Status::invalid_argument(rejection.body_text()))
+    }
+}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        let status = self.0;
+        let info = error_info(&status);
+        let body = ErrorBody {
+            code: status.code() as i32,
+            message: status.message().to_string(),
+            reason: info.as_ref().map(|info| info.reason.clone()),
+            metadata: info.map(|info| info.metadata).unwrap_or_default(),
+        };
+        (http_status(status.code()), Json(body)).into_response()
+    }
+}
+
+/// The HTTP mapping of gRPC codes from `google/rpc/code.proto`.
+pub fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::Cancelled => StatusCode::from_u16(499).unwrap(),
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+            StatusCode::BAD_REQUEST
+        }
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::Unknown | Code::Internal | Code::DataLoss => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Route<S> {
+    service: S,
+    auth: Authenticator,
+}
+
+impl<S> Route<S> {
+    /// Checks the credentials in `headers` before the body is decoded.
+    fn authenticate(
+        &self, uri: &Uri, headers: HeaderMap,
+    ) -> Result<Option<ClientIdentity>, GatewayError> {
+        let identity = self
+            .auth
+            .authenticate(uri.path(), &MetadataMap::from_headers(headers))
+            .map_err(Status::from)?;
+        Ok(identity)
+    }
+}
+
+/// Wraps `message` as a request of the authenticated client.
+fn request<T>(identity: Option<ClientIdentity>, message: T) -> Request<T> {
+    let mut request = Request::new(message);
+    if let Some(identity) = identity {
+        request.extensions_mut().insert(identity);
+    }
+    request
+}
+
+/// JSON routes over the same services as the gRPC server, with the routes of
+/// a disabled service left out. Every route checks the same credentials as
+/// the gRPC services, sent as HTTP headers.
+pub fn router(
+    encoder: Option<EncoderService>, signer: Option<SignerService>,
+    auth: Authenticator, max_body_size: usize,
+) -> Router {
+    let mut router = Router::new();
+    if let Some(encoder) = encoder {
+        router = router.merge(
+            Router::new()
+                .route("/v1/encode", post(encode))
+                .route_layer(from_fn(metrics::record_http_request))
+                .with_state(Route {
+                    service: encoder,
+                    auth: auth.clone(),
+                }),
+        );
+    }
+    if let Some(signer) = signer {
+        router = router.merge(
+            Router::new()
+                .route("/v1/verify", post(verify))
+                .route("/v1/recover", post(recover))
+                .route_layer(from_fn(metrics::record_http_request))
+                .with_state(Route {
+                    service: signer,
+                    auth,
+                }),
+        );
+    }
+    router.layer(DefaultBodyLimit::max(max_body_size))
+}
+
+pub async fn serve(
+    addr: SocketAddr, router: Router, signal: impl Future<Output = ()>,
+) -> Result<(), hyper::Error> {
+    axum::Server::try_bind(&addr)?
+        .serve(router.into_make_service())
+        .with_graceful_shutdown(signal)
+        .await
+}
+
+async fn encode(
+    State(route): State<Route<EncoderService>>, uri: Uri, headers: HeaderMap,
+    body: Result<Json<EncodeBody>, JsonRejection>,
+) -> Result<Json<EncodeResponse>, GatewayError> {
+    let identity = route.authenticate(&uri, headers)?;
+    let Json(body) = body?;
+    let message = EncodeBlobRequest {
+        data: body.encoding.decode("data", &body.data)?,
+        require_data: body.require_data,
+        light_slice: body.light_slice,
+        row_indices: body.row_indices,
+        commitment_format: body.commitment_format.into(),
+    };
+
+    let reply = route
+        .service
+        .encode_blob(request(identity, message))
+        .await?
+        .into_inner();
+    let encoding = body.encoding;
+    Ok(Json(EncodeResponse {
+        version: reply.version,
+        erasure_commitment: encoding.encode(&reply.erasure_commitment),
+        storage_root: encoding.encode(&reply.storage_root),
+        encoded_data: encoding.encode(&reply.encoded_data),
+        encoded_slice: reply
+            .encoded_slice
+            .iter()
+            .map(|slice| encoding.encode(slice))
+            .collect(),
+    }))
+}
+
+async fn verify(
+    State(route): State<Route<SignerService>>, uri: Uri, headers: HeaderMap,
+    body: Result<Json<VerifyBody>, JsonRejection>,
+) -> Result<Json<VerifyResponse>, GatewayError> {
+    let identity = route.authenticate(&uri, headers)?;
+    let Json(body) = body?;
+    let encoding = body.encoding;
+    let message = VerifySlicesRequest {
+        erasure_commitment: encoding
+            .decode("erasure_commitment", &body.erasure_commitment)?,
+        storage_root: encoding.decode("storage_root", &body.storage_root)?,
+        encoded_slice: body
+            .encoded_slice
+            .iter()
+            .map(|slice| encoding.decode("encoded_slice", slice))
+            .collect::<Result<_, _>>()?,
+        commitment_format: body.commitment_format.into(),
+    };
+
+    let reply = route
+        .service
+        .verify_slices(request(identity, message))
+        .await?
+        .into_inner();
+    Ok(Json(VerifyResponse {
+        results: reply.results,
+    }))
+}
+
+async fn recover(
+    State(route): State<Route<SignerService>>, uri: Uri, headers: HeaderMap,
+    body: Result<Json<RecoverBody>, JsonRejection>,
+) -> Result<Json<RecoverResponse>, GatewayError> {
+    let identity = route.authenticate(&uri, headers)?;
+    let Json(body) = body?;
+    let encoding = body.encoding;
+    let message = RecoverBlobRequest {
+        rows: body
+            .rows
+            .iter()
+            .map(|(index, row)| Ok((*index, encoding.decode("rows", row)?)))
+            .collect::<Result<_, Status>>()?,
+        verify: body.verify,
+        erasure_commitment: encoding
+            .decode("erasure_commitment", &body.erasure_commitment)?,
+        storage_root: encoding.decode("storage_root", &body.storage_root)?,
+        commitment_format: body.commitment_format.into(),
+    };
+
+    let reply = route
+        .service
+        .recover_blob(request(identity, message))
+        .await?
+        .into_inner();
+    Ok(Json(RecoverResponse {
+        data: encoding.encode(&reply.data),
+        dropped_rows: reply.dropped_rows,
+    }))
+}
@@ -0,0 +1,165 @@
+use std::{
+    convert::Infallible,
+    future::{self, Future},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use hyper::Body;
+use once_cell::sync::OnceCell;
+use tonic::{body::BoxBody, codegen::http, server::NamedService, Status};
+use tonic_health::{server::HealthReporter, ServingStatus};
+use tower::{Service, ServiceExt};
+use tracing::warn;
+
+/// Registered with the server before its params are loaded, so health checks
+/// and reflection work meanwhile. Calls fail with `UNAVAILABLE` until `set`.
+pub struct Deferred<S> {
+    inner: Arc<OnceCell<S>>,
+}
+
+impl<S> Deferred<S> {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(OnceCell::new()),
+        }
+    }
+
+    pub fn set(&self, service: S) {
+        if self.inner.set(service).is_err() {
+            warn!("Deferred service is already set");
+        }
+    }
+}
+
+impl<S> Clone for Deferred<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S: NamedService> NamedService for Deferred<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S> Service<http::Request<Body>> for Deferred<S>
+where
+    S: Service<
+            http::Request<Body>,
+            Response = http::Response<BoxBody>,
+            Error = Infallible,
+        > + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Error = Infallible;
+    type Future = Pin<
+        Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>,
+    >;
+    type Response = http::Response<BoxBody>;
+
+    fn poll_ready(
+        &mut self, _cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        match self.inner.get() {
+            Some(service) => Box::pin(service.clone().oneshot(request)),
+            None => Box::pin(future::ready(Ok(Status::unavailable(
+                "Server is loading its params",
+            )
+            .to_http()))),
+        }
+    }
+}
+
+/// Sets the status of the server, reported under the empty service name, and
+/// of each of `services`.
+pub async fn set_status(
+    reporter: &mut HealthReporter, services: &[&str], status: ServingStatus,
+) {
+    reporter.set_service_status("", status).await;
+    for service in services {
+        reporter.set_service_status(*service, status).await;
+    }
+}
+
+/// Reports `NOT_SERVING` like `set_status`, then ends the `Watch` streams of
+/// the server and `services`, which would otherwise hold a graceful shutdown
+/// open. Checks keep answering `NOT_SERVING`.
+pub async fn set_draining(reporter: &mut HealthReporter, services: &[&str]) {
+    set_status(reporter, services, ServingStatus::NotServing).await;
+    // Watchers still receive the status above before their stream ends
+    for service in std::iter::once(&"").chain(services) {
+        reporter.clear_service_status(service).await;
+    }
+    set_status(reporter, services, ServingStatus::NotServing).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{
+        transport::{Endpoint, Server},
+        Code,
+    };
+    use tonic_health::{
+        pb::{
+            health_check_response::ServingStatus as Status,
+            health_client::HealthClient, HealthCheckRequest,
+        },
+        ServingStatus,
+    };
+
+    use super::{set_status, Deferred};
+
+    #[tokio::test]
+    async fn test_deferred_health() {
+        let (mut reporter, health_service) =
+            tonic_health::server::health_reporter();
+        let deferred = Deferred::new();
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(deferred.clone())
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let channel = Endpoint::from_shared(format!("http://{addr}"))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let mut client = HealthClient::new(channel);
+        let check = |service: &str| HealthCheckRequest {
+            service: service.into(),
+        };
+
+        let status = client.check(check("")).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+
+        deferred.set(health_service);
+        set_status(
+            &mut reporter,
+            &["encoder.Encoder"],
+            ServingStatus::NotServing,
+        )
+        .await;
+        let reply = client.check(check("encoder.Encoder")).await.unwrap();
+        assert_eq!(reply.into_inner().status(), Status::NotServing);
+
+        set_status(&mut reporter, &["encoder.Encoder"], ServingStatus::Serving)
+            .await;
+        for service in ["", "encoder.Encoder"] {
+            let reply = client.check(check(service)).await.unwrap();
+            assert_eq!(reply.into_inner().status(), Status::Serving);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use tokio::sync::watch;
+
+use crate::{
+    error::ServiceError,
+    service::encoder::{EncodeBlobReply, EncodeJob, JobState},
+};
+
+#[derive(Debug, Clone)]
+pub struct JobConfig {
+    /// How long a finished job's result stays available.
+    pub result_ttl: Duration,
+    /// Jobs queued or running at once before new ones are rejected. Finished
+    /// jobs do not count.
+    pub max_jobs: usize,
+}
+
+impl Default for JobConfig {
+    fn default() -> Self {
+        Self {
+            result_ttl: Duration::from_secs(600),
+            max_jobs: 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done(EncodeBlobReply),
+    Failed(String),
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Done(_) | JobStatus::Failed(_))
+    }
+
+    pub fn into_proto(self, job_id: String) -> EncodeJob {
+        let (state, reply, error) = match self {
+            JobStatus::Queued => (JobState::Queued, None, String::new()),
+            JobStatus::Running => (JobState::Running, None, String::new()),
+            JobStatus::Done(reply) => {
+                (JobState::Done, Some(reply), String::new())
+            }
+            JobStatus::Failed(error) => (JobState::Failed, None, error),
+        };
+        EncodeJob {
+            job_id,
+            state: state.into(),
+            reply,
+            error,
+        }
+    }
+}
+
+struct Job {
+    status: watch::Sender<JobStatus>,
+    finished_at: Option<Instant>,
+}
+
+/// In-process table of encode jobs started by `SubmitEncode`. Finished jobs
+/// are dropped once their result is older than the TTL, and submissions are
+/// rejected while too many jobs are unfinished.
+pub struct JobTable {
+    jobs: Mutex<HashMap<String, Job>>,
+    /// Jobs queued or running, which shutdown waits for
+    unfinished: watch::Sender<usize>,
+    result_ttl: Duration,
+    max_jobs: usize,
+}
+
+impl JobTable {
+    pub fn new(config: &JobConfig) -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            unfinished: watch::channel(0).0,
+            result_ttl: config.result_ttl,
+            max_jobs: config.max_jobs,
+        }
+    }
+
+    /// Registers a queued job and returns its id, or fails with
+    /// `TooManyJobs` when `max_jobs` jobs are unfinished.
+    pub fn submit(&self) -> Result<String, ServiceError> {
+        let job_id = hex::encode(rand::thread_rng().gen::<[u8; 16]>());
+        let mut jobs = self.jobs.lock().unwrap();
+        self.purge_expired(&mut jobs);
+        if self.unfinished() >= self.max_jobs {
+            return Err(ServiceError::TooManyJobs {
+                max_jobs: self.max_jobs,
+            });
+        }
+        self.unfinished.send_modify(|unfinished| *unfinished += 1);
+        jobs.insert(
+            job_id.clone(),
+            Job {
+                status: watch::channel(JobStatus::Queued).0,
+                finished_at: None,
+            },
+        );
+        Ok(job_id)
+    }
+
+    pub fn update(&self, job_id: &str, status: JobStatus) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.get_mut(job_id) {
+            if status.is_finished() && job.finished_at.is_none() {
+                job.finished_at = Some(Instant::now());
+                self.unfinished.send_modify(|unfinished| *unfinished -= 1);
+            }
+            job.status.send_replace(status);
+        }
+    }
+
+    pub fn get(&self, job_id: &str) -> Result<JobStatus, ServiceError> {
+        Ok(self.subscribe(job_id)?.borrow().clone())
+    }
+
+    /// Waits until the job is done or failed.
+    pub async fn wait(&self, job_id: &str) -> Result<JobStatus, ServiceError> {
+        let mut status = self.subscribe(job_id)?;
+        let finished = status
+            .wait_for(JobStatus::is_finished)
+            .await
+            .map_err(|_| ServiceError::JobNotFound(job_id.to_string()))?
+            .clone();
+        Ok(finished)
+    }
+
+    pub fn len(&self) -> usize { self.jobs.lock().unwrap().len() }
+
+    /// Jobs queued or running.
+    pub fn unfinished(&self) -> usize { *self.unfinished.borrow() }
+
+    /// Waits until no job is queued or running.
+    pub async fn drained(&self) {
+        let mut unfinished = self.unfinished.subscribe();
+        // The sender lives in `self`
+        let _ = unfinished.wait_for(|unfinished| *unfinished == 0).await;
+    }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    fn subscribe(
+        &self, job_id: &str,
+    ) -> Result<watch::Receiver<JobStatus>, ServiceError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        self.purge_expired(&mut jobs);
+        jobs.get(job_id)
+            .map(|job| job.status.subscribe())
+            .ok_or_else(|| ServiceError::JobNotFound(job_id.to_string()))
+    }
+
+    fn purge_expired(&self, jobs: &mut HashMap<String, Job>) {
+        jobs.retain(|_, job| {
+            job.finished_at.map_or(true, |finished_at| {
+                finished_at.elapsed() < self.result_ttl
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::{JobConfig, JobStatus, JobTable};
+    use crate::{service::encoder::EncodeBlobReply, ServiceError};
+
+    #[tokio::test]
+    async fn test_job_lifecycle() {
+        let jobs = Arc::new(JobTable::new(&JobConfig::default()));
+        let job_id = jobs.submit().unwrap();
+        assert_eq!(jobs.get(&job_id).unwrap(), JobStatus::Queued);
+
+        let waiter = tokio::spawn({
+            let jobs = jobs.clone();
+            let job_id = job_id.clone();
+            async move { jobs.wait(&job_id).await }
+        });
+        jobs.update(&job_id, JobStatus::Running);
+        assert_eq!(jobs.get(&job_id).unwrap(), JobStatus::Running);
+
+        let done = JobStatus::Done(EncodeBlobReply::default());
+        jobs.update(&job_id, done.clone());
+        assert_eq!(waiter.await.unwrap().unwrap(), done);
+        assert!(jobs.get("unknown").is_err());
+    }
+
+    #[test]
+    fn test_result_ttl() {
+        let jobs = JobTable::new(&JobConfig {
+            result_ttl: Duration::ZERO,
+            ..Default::default()
+        });
+        let running = jobs.submit().unwrap();
+        jobs.update(&running, JobStatus::Running);
+        let failed = jobs.submit().unwrap();
+        jobs.update(&failed, JobStatus::Failed("error".into()));
+
+        assert!(jobs.get(&failed).is_err());
+        assert_eq!(jobs.get(&running).unwrap(), JobStatus::Running);
+        assert_eq!(jobs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_max_jobs() {
+        let jobs = JobTable::new(&JobConfig {
+            max_jobs: 1,
+            ..Default::default()
+        });
+        let job_id = jobs.submit().unwrap();
+        assert!(matches!(
+            jobs.submit(),
+            Err(ServiceError::TooManyJobs { max_jobs: 1 })
+        ));
+        assert!(tokio::time::timeout(
+            Duration::from_millis(10),
+            jobs.drained()
+        )
+        .await
+        .is_err());
+
+        // Finished jobs make room while their result is kept
+        jobs.update(&job_id, JobStatus::Failed("error".into()));
+        jobs.drained().await;
+        let next = jobs.submit().unwrap();
+        assert_eq!((jobs.len(), jobs.unfinished()), (2, 1));
+        assert!(jobs.get(&job_id).is_ok());
+
+        jobs.update(&next, JobStatus::Running);
+        assert_eq!(jobs.unfinished(), 1);
+    }
+}
@@ -1,27 +1,470 @@
+mod admin;
+mod auth;
+mod cache;
+mod cancel;
+pub mod client;
+mod error;
+mod executor;
+pub mod gateway;
+mod health;
+mod jobs;
+pub mod metrics;
 mod service;
+mod tls;
 
-pub use service::{encoder::EncodeBlobReply, EncoderServer, EncoderService};
+pub use admin::{AdminServer, AdminService};
+pub use auth::{
+    record_path, sign_request, AuthConfig, Authenticator, ClientIdentity,
+    RequestPath, CLIENT_ID_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+pub use cache::{CacheConfig, CacheStats, ReplyCache};
+pub use cancel::{CancelOnDrop, CancelReason, CancelToken};
+pub use error::{error_info, ErrorInfo, ServiceError};
+pub use executor::{Executor, ExecutorConfig, Reservation};
+pub use jobs::{JobConfig, JobStatus, JobTable};
+pub use service::{
+    encoder::EncodeBlobReply, EncoderServer, EncoderService, ReplyOptions,
+    SignerServer,
+};
+pub use tls::{peer_identity, TlsConfig};
 
-use std::net::SocketAddr;
-use tonic::transport::Server;
-use tracing::info;
+pub use amt::{ParamsCheck, ParamsChecker};
+pub use zg_encoder::SelfTestError;
+
+use axum::middleware::from_fn_with_state;
+use health::Deferred;
+use std::{
+    future::{self, Future},
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use tokio::sync::watch;
+use tonic::{
+    server::NamedService, service::interceptor::InterceptedService,
+    transport::Server,
+};
+use tonic_health::ServingStatus;
+use tower::util::MapRequestLayer;
+use tracing::{field, info, info_span, warn};
 
 pub use service::SignerService;
 
 const MESSAGE_SIZE_LIMIT: usize = 1024 * 1024 * 1024; // 1G
 
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    pub executor: ExecutorConfig,
+    pub cache: CacheConfig,
+    pub jobs: JobConfig,
+    pub auth: AuthConfig,
+    /// Serve over TLS instead of plaintext.
+    pub tls: Option<TlsConfig>,
+    /// Address of the HTTP/JSON gateway, which is off when unset.
+    pub http_listen_address: Option<SocketAddr>,
+    /// Address serving Prometheus metrics at `/metrics`, which is off when
+    /// unset.
+    pub metrics_listen_address: Option<SocketAddr>,
+    /// How long running requests may take to finish after shutdown starts.
+    pub drain_timeout: Duration,
+    /// Largest gRPC message or HTTP body accepted or sent, in bytes.
+    pub max_message_size: usize,
+    /// Serve the Encoder service, which loads the prover params.
+    pub enable_encoder: bool,
+    /// Serve the Signer service, which loads the verifier params.
+    pub enable_signer: bool,
+    /// What a params file not matching the built-in manifest does.
+    pub params_check: ParamsCheck,
+    /// Allow `Admin.ReloadParams` to swap in freshly loaded encoder params.
+    pub allow_params_reload: bool,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            executor: ExecutorConfig::default(),
+            cache: CacheConfig::default(),
+            jobs: JobConfig::default(),
+            auth: AuthConfig::default(),
+            tls: None,
+            http_listen_address: None,
+            metrics_listen_address: None,
+            drain_timeout: Duration::from_secs(30),
+            max_message_size: MESSAGE_SIZE_LIMIT,
+            enable_encoder: true,
+            enable_signer: true,
+            params_check: ParamsCheck::Enforce,
+            allow_params_reload: false,
+        }
+    }
+}
+
 pub async fn run_server(
-    addr: SocketAddr, param_dir: &str,
+    addr: SocketAddr, param_dir: &str, options: ServerOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let encoder_service = EncoderService::new(param_dir);
-    info!("Encoder service ready");
-    Server::builder()
-        .add_service(
-            EncoderServer::new(encoder_service)
-                .max_decoding_message_size(MESSAGE_SIZE_LIMIT)
-                .max_encoding_message_size(MESSAGE_SIZE_LIMIT),
+    run_server_with_shutdown(addr, param_dir, options, future::pending()).await
+}
+
+/// Serves until `signal` resolves, then stops accepting new RPCs and waits up
+/// to the drain timeout for the running ones before returning.
+pub async fn run_server_with_shutdown(
+    addr: SocketAddr, param_dir: &str, options: ServerOptions,
+    signal: impl Future<Output = ()>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    info!(?options, "Server options");
+    let executor = Executor::new(&options.executor);
+    let params_checker = ParamsChecker::new(options.params_check);
+    let in_flight = metrics::InFlight::default();
+    let authenticator = Authenticator::new(&options.auth);
+
+    // The services answer UNAVAILABLE and health checks NOT_SERVING until
+    // their params are loaded and the encoder passed its self-test
+    let (mut health, health_service) = tonic_health::server::health_reporter();
+    let services: Vec<&str> = [
+        (
+            options.enable_encoder,
+            EncoderServer::<EncoderService>::NAME,
+        ),
+        (options.enable_signer, SignerServer::<SignerService>::NAME),
+        (true, AdminServer::<AdminService>::NAME),
+    ]
+    .into_iter()
+    .filter_map(|(enabled, name)| enabled.then_some(name))
+    .collect();
+    health::set_status(&mut health, &services, ServingStatus::NotServing).await;
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(service::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(
+            tonic_health::pb::FILE_DESCRIPTOR_SET,
         )
-        .serve(addr)
-        .await?;
+        .build()?;
+    let encoder_slot = options.enable_encoder.then(Deferred::new);
+    let signer_slot = options.enable_signer.then(Deferred::new);
+    let admin_slot = Deferred::new();
+    let loading = tokio::task::spawn_blocking({
+        let (param_dir, options) = (param_dir.to_string(), options.clone());
+        let (executor, checker) = (executor.clone(), params_checker.clone());
+        move || load_services(&param_dir, &options, executor, &checker)
+    });
+
+    let mut builder = Server::builder()
+        .trace_fn(|request| {
+            info_span!(
+                "request",
+                path = request.uri().path(),
+                client = field::Empty
+            )
+        })
+        .layer(metrics::GrpcMetricsLayer::new(in_flight.clone()))
+        .layer(MapRequestLayer::new(record_path::<hyper::Body>));
+    if let Some(tls) = &options.tls {
+        builder = builder.tls_config(tls.server_config()?)?;
+    }
+
+    let (draining_tx, draining_rx) = watch::channel(false);
+    let draining = || {
+        let mut draining_rx = draining_rx.clone();
+        async move {
+            let _ = draining_rx.wait_for(|draining| *draining).await;
+        }
+    };
+
+    let metrics_server = match options.metrics_listen_address {
+        Some(metrics_addr) => {
+            info!(%metrics_addr, "Starting metrics server");
+            Some(tokio::spawn(gateway::serve(
+                metrics_addr,
+                metrics::router(executor.clone()),
+                draining(),
+            )))
+        }
+        None => None,
+    };
+
+    let server = builder
+        .add_service(health_service)
+        .add_service(reflection)
+        .add_optional_service(encoder_slot.clone())
+        .add_optional_service(signer_slot.clone())
+        .add_service(admin_slot.clone())
+        .serve_with_shutdown(addr, draining());
+    tokio::pin!(server);
+    tokio::pin!(signal);
+
+    let loaded = tokio::select! {
+        result = &mut server => return Ok(result?),
+        _ = &mut signal => None,
+        loaded = loading => {
+            Some(loaded?.map_err(|e| e as Box<dyn std::error::Error>)?)
+        }
+    };
+    let mut gateway = None;
+    let mut jobs = None;
+    if let Some((encoder_service, signer_service)) = loaded {
+        jobs = encoder_service.as_ref().map(EncoderService::jobs);
+        if let Some(http_addr) = options.http_listen_address {
+            let router = gateway::router(
+                encoder_service.clone(),
+                signer_service.clone(),
+                authenticator.clone(),
+                options.max_message_size,
+            )
+            .layer(from_fn_with_state(
+                in_flight.clone(),
+                metrics::track_in_flight,
+            ));
+            info!(%http_addr, "Starting http gateway");
+            gateway = Some(tokio::spawn(gateway::serve(
+                http_addr,
+                router,
+                draining(),
+            )));
+        }
+        let admin_service = AdminService::new(
+            param_dir,
+            executor.clone(),
+            encoder_service.clone(),
+            signer_service.clone(),
+        )
+        .with_started(started)
+        .with_services(&services)
+        .with_reload(options.allow_params_reload)
+        .with_params_checker(params_checker.clone());
+        admin_slot.set(InterceptedService::new(
+            AdminServer::new(admin_service),
+            authenticator.clone(),
+        ));
+        let max_message_size = options.max_message_size;
+        if let (Some(slot), Some(service)) = (&encoder_slot, encoder_service) {
+            slot.set(InterceptedService::new(
+                EncoderServer::new(service)
+                    .max_decoding_message_size(max_message_size)
+                    .max_encoding_message_size(max_message_size),
+                authenticator.clone(),
+            ));
+        }
+        if let (Some(slot), Some(service)) = (&signer_slot, signer_service) {
+            slot.set(InterceptedService::new(
+                SignerServer::new(service)
+                    .max_decoding_message_size(max_message_size)
+                    .max_encoding_message_size(max_message_size),
+                authenticator,
+            ));
+        }
+        health::set_status(&mut health, &services, ServingStatus::Serving)
+            .await;
+        info!("Server is serving");
+
+        tokio::select! {
+            result = &mut server => return Ok(result?),
+            _ = &mut signal => {}
+        }
+    }
+    health::set_draining(&mut health, &services).await;
+    let _ = draining_tx.send(true);
+    let draining_requests = in_flight.count();
+    let unfinished_jobs = || jobs.as_ref().map_or(0, |jobs| jobs.unfinished());
+    let draining_jobs = unfinished_jobs();
+    info!(
+        in_flight = draining_requests,
+        jobs = draining_jobs,
+        drain_timeout = ?options.drain_timeout,
+        "Shutting down, draining requests"
+    );
+    // Submitted jobs run detached from their call, and are dropped with the
+    // runtime unless they finish in time
+    let drained = async {
+        (&mut server).await?;
+        for http_server in [gateway, metrics_server].into_iter().flatten() {
+            http_server.await??;
+        }
+        if let Some(jobs) = &jobs {
+            jobs.drained().await;
+        }
+        Ok::<_, Box<dyn std::error::Error>>(())
+    };
+    match tokio::time::timeout(options.drain_timeout, drained).await {
+        Ok(result) => {
+            result?;
+            info!(
+                drained = draining_requests,
+                aborted = 0,
+                jobs = draining_jobs,
+                "Server stopped"
+            );
+        }
+        Err(_) => {
+            let aborted = in_flight.count();
+            let aborted_jobs = unfinished_jobs();
+            warn!(
+                drained = draining_requests.saturating_sub(aborted),
+                aborted,
+                jobs = draining_jobs.saturating_sub(aborted_jobs),
+                aborted_jobs,
+                "Drain timeout expired, server stopped"
+            );
+        }
+    }
     Ok(())
 }
+
+/// Loads the params of the enabled services, then warms up and self-tests the
+/// encoder. Fails on a params file that cannot be read or, unless
+/// `params_check` is `Warn`, does not match the manifest.
+fn load_services(
+    param_dir: &str, options: &ServerOptions, executor: Executor,
+    check: &ParamsChecker,
+) -> Result<
+    (Option<EncoderService>, Option<SignerService>),
+    Box<dyn std::error::Error + Send + Sync>,
+> {
+    let signer_service = if options.enable_signer {
+        let service = SignerService::new(param_dir, check)?
+            .with_executor(executor.clone());
+        info!("Signer service ready");
+        Some(service)
+    } else {
+        None
+    };
+    let encoder_service = if options.enable_encoder {
+        let service = EncoderService::new(param_dir, check)?
+            .with_executor(executor)
+            .with_cache(ReplyCache::new(&options.cache))
+            .with_jobs(JobTable::new(&options.jobs));
+        service
+            .warmup(signer_service.as_ref().map(|service| &*service.params))?;
+        info!("Encoder service ready");
+        Some(service)
+    } else {
+        None
+    };
+    Ok((encoder_service, signer_service))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use tokio::sync::{oneshot, Notify};
+    use tonic::transport::Endpoint;
+    use tonic_health::pb::{
+        health_check_response::ServingStatus, health_client::HealthClient,
+        HealthCheckRequest,
+    };
+
+    use super::{run_server_with_shutdown, ServerOptions};
+    use crate::{client::EncoderClient, EncoderService};
+
+    const PARAM_DIR: &str = "../crates/amt/pp";
+
+    /// Log output of the server, waking `written` on each line.
+    #[derive(Clone, Default)]
+    struct Logs {
+        text: Arc<Mutex<Vec<u8>>>,
+        written: Arc<Notify>,
+    }
+
+    impl Logs {
+        fn contains(&self, needle: &str) -> bool {
+            String::from_utf8_lossy(&self.text.lock().unwrap()).contains(needle)
+        }
+
+        async fn wait_for(&self, needle: &str) {
+            while !self.contains(needle) {
+                self.written.notified().await;
+            }
+        }
+    }
+
+    impl io::Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.text.lock().unwrap().extend_from_slice(buf);
+            self.written.notify_one();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
+    #[tokio::test]
+    async fn test_drain() {
+        // Creates the test params unless another test did
+        EncoderService::new_for_test(PARAM_DIR);
+        let logs = Logs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer({
+                let logs = logs.clone();
+                move || logs.clone()
+            })
+            .with_ansi(false)
+            .finish();
+        // The server runs on this thread, the test runtime has no others
+        let _subscriber = tracing::subscriber::set_default(subscriber);
+
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let options = ServerOptions {
+            enable_signer: false,
+            drain_timeout: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let server =
+            run_server_with_shutdown(addr, PARAM_DIR, options, async {
+                let _ = stop_rx.await;
+            });
+
+        let client = async {
+            let url = format!("http://{addr}");
+            let channel = loop {
+                match Endpoint::from_shared(url.clone())
+                    .unwrap()
+                    .connect()
+                    .await
+                {
+                    Ok(channel) => break channel,
+                    Err(_) => {
+                        tokio::time::sleep(Duration::from_millis(10)).await
+                    }
+                }
+            };
+            let mut health = HealthClient::new(channel);
+            let encoder = HealthCheckRequest {
+                service: "encoder.Encoder".into(),
+            };
+            logs.wait_for("Server is serving").await;
+            let status = health.check(encoder.clone()).await.unwrap();
+            assert_eq!(status.into_inner().status(), ServingStatus::Serving);
+
+            // Neither call runs on the executor
+            let mut watch = health.watch(encoder).await.unwrap().into_inner();
+            watch.message().await.unwrap();
+            let encode = tokio::spawn(async move {
+                let mut client = EncoderClient::connect(url).await.unwrap();
+                client.encode_blob(&[1, 2, 3]).await
+            });
+            logs.wait_for("Receive encoder task").await;
+            stop_tx.send(()).unwrap();
+
+            encode.await.unwrap().unwrap();
+            // The open watch ends instead of holding the drain open. It may
+            // repeat the status it started with first
+            let mut statuses = vec![];
+            while let Some(status) = watch.message().await.unwrap() {
+                statuses.push(status.status());
+            }
+            assert_eq!(statuses.last(), Some(&ServingStatus::NotServing));
+        };
+
+        let (served, ()) = tokio::join!(server, client);
+        served.unwrap();
+        assert!(logs.contains("Shutting down, draining requests in_flight=2"));
+        assert!(logs.contains("Server stopped drained=2 aborted=0"));
+    }
+}
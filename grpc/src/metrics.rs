@@ -0,0 +1,480 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{ready, Context, Poll},
+    time::{Instant, SystemTime},
+};
+
+use axum::{
+    extract::{MatchedPath, State},
+    http::{header::CONTENT_TYPE, HeaderMap, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use hyper::body::{HttpBody, SizeHint};
+use once_cell::sync::Lazy;
+use prometheus::{
+    exponential_buckets, HistogramOpts, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, Opts, Registry, TextEncoder, TEXT_FORMAT,
+};
+use tonic::{Code, Status};
+use tower::{Layer, Service};
+use tracing::{
+    span::{Attributes, Id},
+    Subscriber,
+};
+use tracing_subscriber::{
+    filter::filter_fn, layer::Context as LayerContext, registry::LookupSpan,
+};
+
+use crate::{cancel::CancelReason, executor::Executor};
+
+/// Spans whose durations are exported as `zg_encoder_stage_duration_seconds`.
+pub const STAGE_SPANS: &[&str] = &[
+    "encode",
+    "encode_rows",
+    "encode_amt",
+    "encode_merkle",
+    "gen_amt_proofs",
+    "load_amt_params",
+    "load_amt_verify_params",
+    "verify",
+    "recover",
+];
+
+static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+struct Metrics {
+    registry: Registry,
+    grpc_requests: IntCounterVec,
+    http_requests: IntCounterVec,
+    input_bytes: HistogramVec,
+    stage_seconds: HistogramVec,
+    queue_depth: IntGauge,
+    in_flight: IntGauge,
+    cache_hits: IntCounter,
+    cache_misses: IntCounter,
+    cancelled: IntCounterVec,
+    last_error: Mutex<Option<LastError>>,
+}
+
+/// The latest gRPC call that failed other than by client cancellation.
+#[derive(Debug, Clone)]
+pub struct LastError {
+    pub method: String,
+    pub code: Code,
+    pub message: String,
+    pub time: SystemTime,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let grpc_requests = IntCounterVec::new(
+            Opts::new(
+                "zg_encoder_grpc_requests_total",
+                "gRPC requests by method and status code",
+            ),
+            &["method", "code"],
+        )
+        .unwrap();
+        let http_requests = IntCounterVec::new(
+            Opts::new(
+                "zg_encoder_http_requests_total",
+                "HTTP gateway requests by route and status",
+            ),
+            &["route", "status"],
+        )
+        .unwrap();
+        let input_bytes = HistogramVec::new(
+            HistogramOpts::new(
+                "zg_encoder_input_bytes",
+                "Size of the blobs, slices or rows in a request",
+            )
+            .buckets(exponential_buckets(4096.0, 4.0, 9).unwrap()),
+            &["method"],
+        )
+        .unwrap();
+        let stage_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "zg_encoder_stage_duration_seconds",
+                "Time spent in each encode, verify and recover stage",
+            )
+            .buckets(exponential_buckets(0.005, 2.0, 15).unwrap()),
+            &["stage"],
+        )
+        .unwrap();
+        let queue_depth = IntGauge::new(
+            "zg_encoder_queue_depth",
+            "Encodes waiting for a free slot",
+        )
+        .unwrap();
+        let in_flight = IntGauge::new(
+            "zg_encoder_in_flight",
+            "Encodes running or waiting for a slot",
+        )
+        .unwrap();
+        let cache_hits = IntCounter::new(
+            "zg_encoder_reply_cache_hits_total",
+            "Encodes answered from the reply cache",
+        )
+        .unwrap();
+        let cache_misses = IntCounter::new(
+            "zg_encoder_reply_cache_misses_total",
+            "Encodes not found in the reply cache",
+        )
+        .unwrap();
+        let cancelled = IntCounterVec::new(
+            Opts::new(
+                "zg_encoder_cancelled_encodes_total",
+                "Encodes stopped between stages because the deadline expired \
+                 or the client went away",
+            ),
+            &["reason"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(grpc_requests.clone())).unwrap();
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(input_bytes.clone())).unwrap();
+        registry.register(Box::new(stage_seconds.clone())).unwrap();
+        registry.register(Box::new(queue_depth.clone())).unwrap();
+        registry.register(Box::new(in_flight.clone())).unwrap();
+        registry.register(Box::new(cache_hits.clone())).unwrap();
+        registry.register(Box::new(cache_misses.clone())).unwrap();
+        registry.register(Box::new(cancelled.clone())).unwrap();
+
+        Self {
+            registry,
+            grpc_requests,
+            http_requests,
+            input_bytes,
+            stage_seconds,
+            queue_depth,
+            in_flight,
+            cache_hits,
+            cache_misses,
+            cancelled,
+            last_error: Mutex::new(None),
+        }
+    }
+}
+
+/// All metrics in the Prometheus text format.
+pub fn render() -> Result<String, prometheus::Error> {
+    TextEncoder::new().encode_to_string(&METRICS.registry.gather())
+}
+
+pub(crate) fn observe_input(method: &str, bytes: usize) {
+    METRICS
+        .input_bytes
+        .with_label_values(&[method])
+        .observe(bytes as f64);
+}
+
+pub(crate) fn record_cache_lookup(hit: bool) {
+    if hit {
+        METRICS.cache_hits.inc();
+    } else {
+        METRICS.cache_misses.inc();
+    }
+}
+
+pub(crate) fn record_cancelled(reason: CancelReason) {
+    METRICS
+        .cancelled
+        .with_label_values(&[reason.as_str()])
+        .inc();
+}
+
+pub fn last_error() -> Option<LastError> {
+    METRICS.last_error.lock().unwrap().clone()
+}
+
+fn record_grpc_request(method: &str, status: &Status) {
+    let code = status.code();
+    METRICS
+        .grpc_requests
+        .with_label_values(&[method, &format!("{:?}", code)])
+        .inc();
+    if !matches!(code, Code::Ok | Code::Cancelled) {
+        *METRICS.last_error.lock().unwrap() = Some(LastError {
+            method: method.to_string(),
+            code,
+            message: status.message().to_string(),
+            time: SystemTime::now(),
+        });
+    }
+}
+
+/// Serves `GET /metrics`, refreshing the queue gauges from `executor` on each
+/// scrape.
+pub fn router(executor: Executor) -> Router {
+    Router::new()
+        .route("/metrics", get(scrape))
+        .with_state(executor)
+}
+
+async fn scrape(State(executor): State<Executor>) -> Response {
+    METRICS.queue_depth.set(executor.queue_depth() as i64);
+    METRICS.in_flight.set(executor.in_flight() as i64);
+    match render() {
+        Ok(text) => ([(CONTENT_TYPE, TEXT_FORMAT)], text).into_response(),
+        Err(e) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Counts gateway requests by matched route, for `Router::route_layer`.
+pub(crate) async fn record_http_request<B>(
+    route: MatchedPath, request: Request<B>, next: Next<B>,
+) -> Response {
+    let response = next.run(request).await;
+    METRICS
+        .http_requests
+        .with_label_values(&[route.as_str(), response.status().as_str()])
+        .inc();
+    response
+}
+
+/// Requests being served, counted from their arrival until the response body
+/// is finished or dropped. Shared by the gRPC server and the HTTP gateway to
+/// report how many requests a shutdown drained or aborted.
+#[derive(Debug, Clone, Default)]
+pub struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    pub fn count(&self) -> usize { self.0.load(Ordering::Relaxed) }
+
+    fn enter(&self) -> InFlightGuard {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard(self.0.clone())
+    }
+}
+
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) { self.0.fetch_sub(1, Ordering::Relaxed); }
+}
+
+/// Counts gateway requests in `in_flight`, for `Router::layer` with
+/// `from_fn_with_state`.
+pub(crate) async fn track_in_flight<B>(
+    State(in_flight): State<InFlight>, request: Request<B>, next: Next<B>,
+) -> Response {
+    let _in_flight = in_flight.enter();
+    next.run(request).await
+}
+
+/// Counts gRPC requests by method and final status code, read from the
+/// response headers of trailers-only replies and from the trailers otherwise,
+/// and keeps the latest failure for `last_error`. Running requests are
+/// counted in `in_flight`.
+#[derive(Debug, Clone, Default)]
+pub struct GrpcMetricsLayer {
+    in_flight: InFlight,
+}
+
+impl GrpcMetricsLayer {
+    pub fn new(in_flight: InFlight) -> Self { Self { in_flight } }
+}
+
+impl<S> Layer<S> for GrpcMetricsLayer {
+    type Service = GrpcMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcMetrics {
+            inner,
+            in_flight: self.in_flight.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GrpcMetrics<S> {
+    inner: S,
+    in_flight: InFlight,
+}
+
+impl<S, B, ResBody> Service<Request<B>> for GrpcMetrics<S>
+where
+    S: Service<Request<B>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    ResBody: HttpBody + Unpin,
+{
+    type Error = S::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, S::Error>> + Send>>;
+    type Response = Response<RecordedBody<ResBody>>;
+
+    fn poll_ready(
+        &mut self, cx: &mut Context<'_>,
+    ) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let method = request
+            .uri()
+            .path()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let in_flight = self.in_flight.enter();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            let method = match Status::from_header_map(response.headers()) {
+                // Unknown paths are not labelled by name, to bound the series
+                Some(status) if status.code() == Code::Unimplemented => {
+                    record_grpc_request("unknown", &status);
+                    None
+                }
+                Some(status) => {
+                    record_grpc_request(&method, &status);
+                    None
+                }
+                None => Some(method),
+            };
+            Ok(response.map(|inner| RecordedBody {
+                inner,
+                method,
+                _in_flight: in_flight,
+            }))
+        })
+    }
+}
+
+/// A response body that records its status from the trailers, or as
+/// `Cancelled` if it is dropped before them.
+pub struct RecordedBody<B> {
+    inner: B,
+    method: Option<String>,
+    _in_flight: InFlightGuard,
+}
+
+impl<B: HttpBody + Unpin> HttpBody for RecordedBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>, cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_data(cx)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>, cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let trailers = ready!(Pin::new(&mut self.inner).poll_trailers(cx));
+        if let Some(method) = self.method.take() {
+            let status = match &trailers {
+                Ok(Some(trailers)) => Status::from_header_map(trailers)
+                    .unwrap_or_else(|| Status::new(Code::Ok, "")),
+                _ => Status::unknown("Response body failed"),
+            };
+            record_grpc_request(&method, &status);
+        }
+        Poll::Ready(trailers)
+    }
+
+    fn is_end_stream(&self) -> bool { self.inner.is_end_stream() }
+
+    fn size_hint(&self) -> SizeHint { self.inner.size_hint() }
+}
+
+impl<B> Drop for RecordedBody<B> {
+    fn drop(&mut self) {
+        if let Some(method) = self.method.take() {
+            record_grpc_request(&method, &Status::cancelled(""));
+        }
+    }
+}
+
+struct Started(Instant);
+
+/// Observes the duration of the `STAGE_SPANS` spans from creation to close.
+pub struct StageTimingLayer;
+
+/// A `StageTimingLayer` that sees the stage spans whatever the log level.
+pub fn stage_layer<S>() -> impl tracing_subscriber::Layer<S> + Send + Sync
+where S: Subscriber + for<'a> LookupSpan<'a> {
+    tracing_subscriber::Layer::with_filter(
+        StageTimingLayer,
+        filter_fn(|metadata| {
+            metadata.is_span() && STAGE_SPANS.contains(&metadata.name())
+        }),
+    )
+}
+
+impl<S> tracing_subscriber::Layer<S> for StageTimingLayer
+where S: Subscriber + for<'a> LookupSpan<'a>
+{
+    fn on_new_span(
+        &self, _attrs: &Attributes<'_>, id: &Id, ctx: LayerContext<'_, S>,
+    ) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Started(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let started = span.extensions().get::<Started>().map(|s| s.0);
+        if let Some(started) = started {
+            METRICS
+                .stage_seconds
+                .with_label_values(&[span.name()])
+                .observe(started.elapsed().as_secs_f64());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::ServiceExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::{observe_input, router, stage_layer};
+    use crate::{Executor, ExecutorConfig};
+
+    #[tokio::test]
+    async fn test_scrape() {
+        let subscriber = tracing_subscriber::registry().with(stage_layer());
+        tracing::subscriber::with_default(subscriber, || {
+            let _amt = tracing::debug_span!("encode_amt").entered();
+            let _other = tracing::info_span!("not_a_stage").entered();
+        });
+        observe_input("EncodeBlob", 1234);
+
+        let request = axum::http::Request::get("/metrics")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router(Executor::new(&ExecutorConfig::default()))
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(text.contains(
+            "zg_encoder_stage_duration_seconds_count{stage=\"encode_amt\"}"
+        ));
+        assert!(!text.contains("not_a_stage"));
+        assert!(text
+            .contains("zg_encoder_input_bytes_count{method=\"EncodeBlob\"}"));
+        assert!(text.contains("zg_encoder_queue_depth 0"));
+    }
+}
@@ -1,88 +1,548 @@
 #![allow(unused)]
 
-use std::time::Instant;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+    time::Instant,
+};
 
-use ark_std::cfg_into_iter;
+use ark_std::{cfg_into_iter, cfg_iter};
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use tonic::{Code, Request, Response, Status};
-use tracing::{debug, info, instrument};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Code, Request, Response, Status, Streaming};
+use tracing::{debug, info, instrument, warn, Span};
+
+use crate::{
+    auth::record_client,
+    cache::{CacheKey, CacheStats, ReplyCache},
+    cancel::{CancelReason, CancelToken},
+    error::ServiceError,
+    executor::Executor,
+    jobs::{JobStatus, JobTable},
+    metrics,
+    tls::peer_identity,
+};
 
 pub mod encoder {
     tonic::include_proto!("encoder");
 }
 
-pub use encoder::encoder_server::EncoderServer;
-use encoder::{encoder_server::Encoder, EncodeBlobReply, EncodeBlobRequest};
+pub(crate) const FILE_DESCRIPTOR_SET: &[u8] =
+    tonic::include_file_descriptor_set!("encoder_descriptor");
+
+use encoder::{
+    encode_blob_rows_reply::Payload, encode_blobs_item,
+    encoder_server::Encoder, signer_server::Signer, DroppedRow,
+    EncodeBlobChunk, EncodeBlobHeader, EncodeBlobReply, EncodeBlobRequest,
+    EncodeBlobRowsReply, EncodeBlobsItem, EncodeBlobsReply, EncodeBlobsRequest,
+    EncodeJob, EncodeResultRequest, EncodedRow, RecoverBlobReply,
+    RecoverBlobRequest, SliceResult, SubmitEncodeReply, VerifySlicesReply,
+    VerifySlicesRequest,
+};
+pub use encoder::{encoder_server::EncoderServer, signer_server::SignerServer};
 
 use amt::{
-    ec_algebra::{CanonicalSerialize, CurveGroup},
-    EncoderParams, PowerTau, VerifierParams,
+    ec_algebra::{CanonicalDeserialize, CanonicalSerialize, CurveGroup},
+    DeferredVerifier, EncoderParams, ParamsChecker, ParamsIntegrityError,
+    PowerTau, VerifierParams,
 };
+use recovery::recover_from_lines;
 use zg_encoder::{
+    commitment_from_bytes, commitment_to_bytes,
     constants::{
-        Scalar, BLOB_COL_LOG, BLOB_ROW_ENCODED, BLOB_ROW_LOG, COSET_N, PE,
+        G1Curve, Scalar, BLOB_COL_LOG, BLOB_COL_N, BLOB_ROW_ENCODED,
+        BLOB_ROW_LOG, COSET_N, PE,
     },
-    EncodedBlob, EncodedSlice, EncoderError, RawBlob, RawData, ZgEncoderParams,
-    ZgSignerParams,
+    self_test, CommitmentFormat, EncodedBlob, EncodedSlice, EncoderError,
+    RawBlob, RawData, RawDataBuilder, SelfTestAnswer, SelfTestError,
+    VerifierError, ZgEncoderParams, ZgSignerParams,
 };
 
+const ROW_STREAM_BUFFER: usize = 16;
+
+#[derive(Clone)]
 pub struct EncoderService {
-    pub params: ZgEncoderParams, /* TODO: remove pub */
+    params: Arc<RwLock<Arc<ZgEncoderParams>>>,
+    executor: Executor,
+    cache: Option<Arc<ReplyCache>>,
+    jobs: Arc<JobTable>,
 }
 
 impl EncoderService {
-    pub fn new(param_dir: &str) -> Self {
-        let params = EncoderParams::from_dir_mont(param_dir, false, None);
-        Self { params }
+    pub fn new(
+        param_dir: &str, check: &ParamsChecker,
+    ) -> Result<Self, ParamsIntegrityError> {
+        let params =
+            EncoderParams::from_dir_mont(param_dir, false, None, check)?;
+        Ok(Self::from_params(params))
     }
 
     pub fn new_for_test(param_dir: &str) -> Self {
-        let params = EncoderParams::from_dir_mont(param_dir, true, None);
-        Self { params }
+        let params = EncoderParams::from_dir_mont(
+            param_dir,
+            true,
+            None,
+            &Default::default(),
+        )
+        .unwrap();
+        Self::from_params(params)
+    }
+
+    pub fn from_params(params: ZgEncoderParams) -> Self {
+        Self {
+            params: Arc::new(RwLock::new(Arc::new(params))),
+            executor: Executor::new(&Default::default()),
+            cache: None,
+            jobs: Arc::new(JobTable::new(&Default::default())),
+        }
+    }
+
+    pub fn with_executor(mut self, executor: Executor) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    pub fn with_cache(mut self, cache: Option<ReplyCache>) -> Self {
+        self.cache = cache.map(Arc::new);
+        self
+    }
+
+    pub fn with_jobs(mut self, jobs: JobTable) -> Self {
+        self.jobs = Arc::new(jobs);
+        self
+    }
+
+    /// The params new encodes use.
+    pub fn params(&self) -> Arc<ZgEncoderParams> {
+        self.params.read().unwrap().clone()
+    }
+
+    /// Swaps in `params` for new encodes and drops the replies cached with the
+    /// old ones. Running encodes finish with the old params.
+    pub fn replace_params(&self, params: ZgEncoderParams) {
+        let mut current = self.params.write().unwrap();
+        *current = Arc::new(params);
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    pub fn warmup(
+        &self, verifier: Option<&ZgSignerParams>,
+    ) -> Result<(), SelfTestError> {
+        warmup_params(&self.params(), verifier)
     }
 }
 
 #[tonic::async_trait]
 impl Encoder for EncoderService {
+    type EncodeBlobRowsStream =
+        ReceiverStream<Result<EncodeBlobRowsReply, Status>>;
+
     async fn encode_blob(
         &self, request: Request<EncodeBlobRequest>,
     ) -> Result<Response<EncodeBlobReply>, Status> {
         let remote_addr = request.remote_addr();
+        record_client(&request);
+        let peer = peer_identity(&request);
+        let cancel = CancelToken::from_request(&request);
         let request_content = request.into_inner();
         info!(
             requester = ?remote_addr,
+            peer = peer.as_deref(),
             data_lenth = request_content.data.len(),
             "Receive encoder task",
         );
+        metrics::observe_input("EncodeBlob", request_content.data.len());
 
+        let raw_data: RawData = request_content
+            .data
+            .as_slice()
+            .try_into()
+            .map_err(ServiceError::from)?;
+
+        let options = ReplyOptions::from(&request_content);
+        let service = self.clone();
+        let _cancel_on_drop = cancel.cancel_on_drop();
         let reply = self
-            .process_data(&request_content.data, request_content.require_data)
-            .map_err(|e| Status::new(Code::Unknown, e))?;
+            .executor
+            .run(move || service.process_raw_data(raw_data, &options, &cancel))
+            .await??;
 
         Ok(Response::new(reply))
     }
+
+    async fn encode_blobs(
+        &self, request: Request<EncodeBlobsRequest>,
+    ) -> Result<Response<EncodeBlobsReply>, Status> {
+        let remote_addr = request.remote_addr();
+        record_client(&request);
+        let cancel = CancelToken::from_request(&request);
+        let request_content = request.into_inner();
+        info!(
+            requester = ?remote_addr,
+            num_blobs = request_content.requests.len(),
+            data_lenth = request_content
+                .requests
+                .iter()
+                .map(|request| request.data.len())
+                .sum::<usize>(),
+            "Receive batch encoder task",
+        );
+        for request in &request_content.requests {
+            metrics::observe_input("EncodeBlobs", request.data.len());
+        }
+
+        let service = self.clone();
+        let _cancel_on_drop = cancel.cancel_on_drop();
+        let replies = self
+            .executor
+            .run(move || {
+                service.process_batch(request_content.requests, &cancel)
+            })
+            .await?;
+
+        Ok(Response::new(EncodeBlobsReply { replies }))
+    }
+
+    async fn encode_blob_stream(
+        &self, request: Request<Streaming<EncodeBlobChunk>>,
+    ) -> Result<Response<EncodeBlobReply>, Status> {
+        let remote_addr = request.remote_addr();
+        record_client(&request);
+        let cancel = CancelToken::from_request(&request);
+        let _cancel_on_drop = cancel.cancel_on_drop();
+        let mut stream = request.into_inner();
+
+        let mut builder = RawDataBuilder::new();
+        let mut options = None;
+        while let Some(chunk) = stream.message().await? {
+            options.get_or_insert(ReplyOptions {
+                require_data: chunk.require_data,
+                light_slice: chunk.light_slice,
+                row_indices: chunk.row_indices.clone(),
+                commitment_format: chunk.commitment_format().into(),
+            });
+            builder
+                .extend_from_slice(&chunk.data)
+                .map_err(ServiceError::from)?;
+        }
+        info!(
+            requester = ?remote_addr,
+            data_lenth = builder.len(),
+            "Receive chunked encoder task",
+        );
+        metrics::observe_input("EncodeBlobStream", builder.len());
+
+        let service = self.clone();
+        let reply = self
+            .executor
+            .run(move || {
+                service.process_raw_data(
+                    builder.build(),
+                    &options.unwrap_or_default(),
+                    &cancel,
+                )
+            })
+            .await??;
+
+        Ok(Response::new(reply))
+    }
+
+    async fn encode_blob_rows(
+        &self, request: Request<EncodeBlobRequest>,
+    ) -> Result<Response<Self::EncodeBlobRowsStream>, Status> {
+        let remote_addr = request.remote_addr();
+        record_client(&request);
+        let cancel = CancelToken::from_request(&request);
+        let request_content = request.into_inner();
+        info!(
+            requester = ?remote_addr,
+            data_lenth = request_content.data.len(),
+            "Receive streaming encoder task",
+        );
+        metrics::observe_input("EncodeBlobRows", request_content.data.len());
+
+        let raw_data: RawData = request_content
+            .data
+            .as_slice()
+            .try_into()
+            .map_err(ServiceError::from)?;
+        let options = ReplyOptions::from(&request_content);
+        options.validate_rows_stream()?;
+
+        let permit = self.executor.acquire().await?;
+        let params = self.params();
+        let (tx, rx) = mpsc::channel(ROW_STREAM_BUFFER);
+        let span = Span::current();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            span.in_scope(|| {
+                stream_rows(&params, raw_data, &options, tx, &cancel)
+            })
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn submit_encode(
+        &self, request: Request<EncodeBlobRequest>,
+    ) -> Result<Response<SubmitEncodeReply>, Status> {
+        let remote_addr = request.remote_addr();
+        record_client(&request);
+        let request_content = request.into_inner();
+
+        let raw_data: RawData = request_content
+            .data
+            .as_slice()
+            .try_into()
+            .map_err(ServiceError::from)?;
+        let options = ReplyOptions::from(&request_content);
+        options.validate()?;
+
+        // Rejects the job now rather than failing it once it reaches the queue
+        let reservation = self.executor.reserve()?;
+        let job_id = self.jobs.submit()?;
+        info!(
+            requester = ?remote_addr,
+            job_id,
+            data_lenth = request_content.data.len(),
+            "Receive encoder job",
+        );
+        metrics::observe_input("SubmitEncode", request_content.data.len());
+
+        let service = self.clone();
+        let id = job_id.clone();
+        tokio::spawn(async move {
+            let jobs = service.jobs.clone();
+            let result = reservation
+                .run({
+                    let jobs = jobs.clone();
+                    let id = id.clone();
+                    move || {
+                        jobs.update(&id, JobStatus::Running);
+                        // Jobs outlive the call, nobody cancels them
+                        service.process_raw_data(
+                            raw_data,
+                            &options,
+                            &CancelToken::default(),
+                        )
+                    }
+                })
+                .await;
+            let status = match result {
+                Ok(Ok(reply)) => JobStatus::Done(reply),
+                Ok(Err(e)) => JobStatus::Failed(e.to_string()),
+                Err(e) => JobStatus::Failed(e.message().to_string()),
+            };
+            jobs.update(&id, status);
+        });
+
+        Ok(Response::new(SubmitEncodeReply { job_id }))
+    }
+
+    async fn get_encode_result(
+        &self, request: Request<EncodeResultRequest>,
+    ) -> Result<Response<EncodeJob>, Status> {
+        let job_id = request.into_inner().job_id;
+        let status = self.jobs.get(&job_id)?;
+        Ok(Response::new(status.into_proto(job_id)))
+    }
+
+    async fn wait_encode_result(
+        &self, request: Request<EncodeResultRequest>,
+    ) -> Result<Response<EncodeJob>, Status> {
+        let job_id = request.into_inner().job_id;
+        let status = self.jobs.wait(&job_id).await?;
+        Ok(Response::new(status.into_proto(job_id)))
+    }
+}
+
+/// Shapes an encode reply, see `EncodeBlobRequest`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ReplyOptions {
+    pub require_data: bool,
+    pub light_slice: bool,
+    /// Rows to return, in this order. Empty means all rows.
+    pub row_indices: Vec<u32>,
+    pub commitment_format: CommitmentFormat,
+}
+
+impl ReplyOptions {
+    pub fn validate(&self) -> Result<(), ServiceError> {
+        match self
+            .row_indices
+            .iter()
+            .find(|&&index| index as usize >= BLOB_ROW_ENCODED)
+        {
+            Some(&index) => Err(ServiceError::InvalidRowIndex {
+                index: index as usize,
+                expected_max: BLOB_ROW_ENCODED,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Validates the options of `EncodeBlobRows`, whose rows are always full
+    /// slices and which sends no `encoded_data`.
+    pub fn validate_rows_stream(&self) -> Result<(), ServiceError> {
+        if self.require_data {
+            return Err(ServiceError::UnsupportedOption("require_data"));
+        }
+        if self.light_slice {
+            return Err(ServiceError::UnsupportedOption("light_slice"));
+        }
+        self.validate()
+    }
+
+    fn rows(&self) -> Vec<usize> {
+        if self.row_indices.is_empty() {
+            (0..BLOB_ROW_ENCODED).collect()
+        } else {
+            self.row_indices
+                .iter()
+                .map(|&index| index as usize)
+                .collect()
+        }
+    }
+}
+
+impl From<encoder::CommitmentFormat> for CommitmentFormat {
+    fn from(format: encoder::CommitmentFormat) -> Self {
+        match format {
+            encoder::CommitmentFormat::ArkUncompressed => {
+                CommitmentFormat::ArkUncompressed
+            }
+            encoder::CommitmentFormat::ArkCompressed => {
+                CommitmentFormat::ArkCompressed
+            }
+            encoder::CommitmentFormat::EvmBigEndian => {
+                CommitmentFormat::EvmBigEndian
+            }
+        }
+    }
+}
+
+impl From<&EncodeBlobRequest> for ReplyOptions {
+    fn from(request: &EncodeBlobRequest) -> Self {
+        Self {
+            require_data: request.require_data,
+            light_slice: request.light_slice,
+            row_indices: request.row_indices.clone(),
+            commitment_format: request.commitment_format().into(),
+        }
+    }
 }
 
 impl EncoderService {
-    #[instrument(skip_all, name = "encode", level = 2)]
     pub fn process_data(
         &self, data: &[u8], require_data: bool,
     ) -> Result<EncodeBlobReply, EncoderError> {
         let raw_data: RawData = data.try_into()?;
-        let raw_blob: RawBlob = raw_data.into();
+        let options = ReplyOptions {
+            require_data,
+            ..Default::default()
+        };
+        self.cached_reply(raw_data, &options, &CancelToken::default())
+    }
+
+    pub fn process_data_with_options(
+        &self, data: &[u8], options: &ReplyOptions,
+    ) -> Result<EncodeBlobReply, ServiceError> {
+        let raw_data: RawData = data.try_into()?;
+        self.process_raw_data(raw_data, options, &CancelToken::default())
+    }
+
+    /// Encodes the blobs one after another on the calling thread, so the
+    /// params and the GPU workspace warmed up here serve the whole batch.
+    /// Once `cancel` is cancelled, the remaining blobs fail without encoding.
+    pub fn process_batch(
+        &self, requests: Vec<EncodeBlobRequest>, cancel: &CancelToken,
+    ) -> Vec<EncodeBlobsItem> {
+        requests
+            .into_iter()
+            .map(|request| {
+                let options = ReplyOptions::from(&request);
+                let result = match RawData::try_from(&request.data[..])
+                    .map_err(ServiceError::from)
+                    .and_then(|raw_data| {
+                        self.process_raw_data(raw_data, &options, cancel)
+                    }) {
+                    Ok(reply) => encode_blobs_item::Result::Reply(reply),
+                    Err(e) => encode_blobs_item::Result::Error(e.into()),
+                };
+                EncodeBlobsItem {
+                    result: Some(result),
+                }
+            })
+            .collect()
+    }
 
-        let encoded_blob = EncodedBlob::build(&raw_blob, &self.params);
+    /// Encodes unless `cancel` is cancelled first, checked between the
+    /// encode stages.
+    pub fn process_raw_data(
+        &self, raw_data: RawData, options: &ReplyOptions, cancel: &CancelToken,
+    ) -> Result<EncodeBlobReply, ServiceError> {
+        options.validate()?;
+        self.cached_reply(raw_data, options, cancel)
+            .map_err(|e| match e {
+                EncoderError::Cancelled => cancel.error(),
+                e => e.into(),
+            })
+    }
 
-        let erasure_commitment = {
-            let c = encoded_blob.get_affine_commitment();
-            let mut answer: Vec<u8> = Vec::new();
-            c.x.serialize_uncompressed(&mut answer).unwrap();
-            c.y.serialize_uncompressed(&mut answer).unwrap();
-            answer
+    /// Returns the cached reply of an identical earlier request if there is
+    /// one, and encodes the data otherwise.
+    fn cached_reply(
+        &self, raw_data: RawData, options: &ReplyOptions, cancel: &CancelToken,
+    ) -> Result<EncodeBlobReply, EncoderError> {
+        let params = self.params();
+        let Some(cache) = &self.cache else {
+            return self.build_reply(&params, raw_data, options, cancel);
         };
-        let storage_root = encoded_blob.get_file_root().to_vec();
-        let encoded_data = if require_data {
+
+        let key = CacheKey::new(raw_data.as_bytes(), options);
+        let cached = cache.get(&key);
+        metrics::record_cache_lookup(cached.is_some());
+        if let Some(reply) = cached {
+            debug!(stats = ?cache.stats(), "Reply cache hit");
+            return Ok(reply);
+        }
+        let reply = self.build_reply(&params, raw_data, options, cancel)?;
+        // Skips replies of params replaced meanwhile, see `replace_params`
+        if Arc::ptr_eq(&params, &self.params.read().unwrap()) {
+            cache.insert(key, reply.clone());
+        }
+        debug!(stats = ?cache.stats(), "Reply cache miss");
+        Ok(reply)
+    }
+
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Number of `SubmitEncode` jobs held, see `JobTable::len`.
+    pub fn job_count(&self) -> usize { self.jobs.len() }
+
+    /// Jobs started by `SubmitEncode`, which run detached from their call.
+    pub fn jobs(&self) -> Arc<JobTable> { self.jobs.clone() }
+
+    #[instrument(skip_all, name = "encode", level = 2)]
+    fn build_reply(
+        &self, params: &ZgEncoderParams, raw_data: RawData,
+        options: &ReplyOptions, cancel: &CancelToken,
+    ) -> Result<EncodeBlobReply, EncoderError> {
+        let encoded_blob = encode_raw_data(raw_data, params, cancel)?;
+
+        let EncodeBlobHeader {
+            version,
+            erasure_commitment,
+            storage_root,
+        } = blob_header(&encoded_blob, options.commitment_format);
+        // light slices are rebuilt from `encoded_data`, so it is always sent
+        let encoded_data = if options.require_data || options.light_slice {
             let data = encoded_blob.get_data();
             let ptr = &data[0][0] as *const u8;
             unsafe { std::slice::from_raw_parts(ptr, data.len() * 32).to_vec() }
@@ -90,65 +550,421 @@ impl EncoderService {
             vec![]
         };
 
-        let encoded_slice: Vec<Vec<u8>> = cfg_into_iter!(0..BLOB_ROW_ENCODED)
-            .map(|row_idx| serailize_to_bytes(&encoded_blob.get_row(row_idx)))
-            .collect();
+        let encoded_slice = cfg_into_iter!(options.rows())
+            .map(|row_idx| {
+                if cancel.is_cancelled() {
+                    return Err(EncoderError::Cancelled);
+                }
+                let row = encoded_blob.get_row(row_idx);
+                Ok(if options.light_slice {
+                    serailize_to_bytes(&row.into_light_slice())
+                } else {
+                    serailize_to_bytes(&row)
+                })
+            })
+            .collect::<Result<Vec<Vec<u8>>, _>>()?;
 
-        let reply = EncodeBlobReply {
-            version: 0,
+        Ok(EncodeBlobReply {
+            version,
             erasure_commitment,
             storage_root,
             encoded_data,
             encoded_slice,
+        })
+    }
+}
+
+/// Warms up `params`, then encodes the built-in self-test payload and checks
+/// it against the known answer of the published params, and against
+/// `verifier` when given.
+pub(crate) fn warmup_params(
+    params: &ZgEncoderParams, verifier: Option<&ZgSignerParams>,
+) -> Result<(), SelfTestError> {
+    params.warmup();
+    let expected = SelfTestAnswer::known();
+    if expected.is_none() {
+        warn!(
+            geometry = ?(BLOB_ROW_LOG, BLOB_COL_LOG, COSET_N),
+            "No self-test answer is recorded for this geometry, only the \
+             verifier checks the self-test encoding"
+        );
+    }
+    let answer = self_test(params, verifier, expected.as_ref())?;
+    info!(%answer, "Self-test passed");
+    Ok(())
+}
+
+fn encode_raw_data(
+    raw_data: RawData, params: &ZgEncoderParams, cancel: &CancelToken,
+) -> Result<EncodedBlob, EncoderError> {
+    let raw_blob: RawBlob = raw_data.into();
+    EncodedBlob::build_cancellable(&raw_blob, params, || cancel.is_cancelled())
+}
+
+fn blob_header(
+    encoded_blob: &EncodedBlob, format: CommitmentFormat,
+) -> EncodeBlobHeader {
+    let erasure_commitment =
+        commitment_to_bytes(&encoded_blob.get_affine_commitment(), format);
+    let storage_root = encoded_blob.get_file_root().to_vec();
+
+    EncodeBlobHeader {
+        version: 0,
+        erasure_commitment,
+        storage_root,
+    }
+}
+
+/// Sends the header and then each serialized row as soon as it is produced,
+/// stopping early once the receiver is dropped or the deadline expires.
+#[instrument(skip_all, name = "encode_rows", level = 2)]
+fn stream_rows(
+    params: &ZgEncoderParams, raw_data: RawData, options: &ReplyOptions,
+    tx: mpsc::Sender<Result<EncodeBlobRowsReply, Status>>,
+    cancel: &CancelToken,
+) {
+    // The call returns with the stream, so the receiver going away is the
+    // only sign of a disconnect
+    let cancelled = || {
+        if tx.is_closed() {
+            cancel.cancel(CancelReason::Disconnected);
+        }
+        cancel.is_cancelled()
+    };
+    let encoded_blob = match EncodedBlob::build_cancellable(
+        &raw_data.into(),
+        params,
+        cancelled,
+    ) {
+        Ok(encoded_blob) => encoded_blob,
+        Err(_) => {
+            let _ = tx.blocking_send(Err(cancel.error().into()));
+            return;
+        }
+    };
+
+    let header =
+        Payload::Header(blob_header(&encoded_blob, options.commitment_format));
+    let rows = options.rows().into_iter().map(|index| {
+        Payload::Row(EncodedRow {
+            index: index as u32,
+            encoded_slice: serailize_to_bytes(&encoded_blob.get_row(index)),
+        })
+    });
+
+    for payload in std::iter::once(header).chain(rows) {
+        if let Err(e) = cancel.check() {
+            let _ = tx.blocking_send(Err(e.into()));
+            return;
+        }
+        let message = EncodeBlobRowsReply {
+            payload: Some(payload),
         };
-        Ok(reply)
+        if tx.blocking_send(Ok(message)).is_err() {
+            debug!("Row stream closed by receiver");
+            return;
+        }
     }
 }
 
+#[derive(Clone)]
 pub struct SignerService {
-    pub params: ZgSignerParams,
+    pub params: Arc<ZgSignerParams>,
+    executor: Executor,
 }
 
 impl SignerService {
-    pub fn new(param_dir: &str) -> Self {
-        let params = VerifierParams::from_dir_mont(param_dir);
-        Self { params }
+    pub fn new(
+        param_dir: &str, check: &ParamsChecker,
+    ) -> Result<Self, ParamsIntegrityError> {
+        let params = VerifierParams::from_dir_mont(param_dir, check)?;
+        Ok(Self {
+            params: Arc::new(params),
+            executor: Executor::new(&Default::default()),
+        })
+    }
+
+    /// Runs verifications and recoveries on `executor`, so they share the
+    /// concurrency limit of the encodes.
+    pub fn with_executor(mut self, executor: Executor) -> Self {
+        self.executor = executor;
+        self
+    }
+}
+
+#[tonic::async_trait]
+impl Signer for SignerService {
+    async fn verify_slices(
+        &self, request: Request<VerifySlicesRequest>,
+    ) -> Result<Response<VerifySlicesReply>, Status> {
+        let remote_addr = request.remote_addr();
+        let request_content = request.into_inner();
+        info!(
+            requester = ?remote_addr,
+            num_slices = request_content.encoded_slice.len(),
+            "Receive verify task",
+        );
+        metrics::observe_input(
+            "VerifySlices",
+            request_content.encoded_slice.iter().map(Vec::len).sum(),
+        );
+
+        let (commitment, storage_root) = decode_authority(
+            &request_content.erasure_commitment,
+            &request_content.storage_root,
+            request_content.commitment_format().into(),
+        )?;
+
+        let params = self.params.clone();
+        let results = self
+            .executor
+            .run(move || {
+                verify_slices(
+                    &params,
+                    &commitment,
+                    &storage_root,
+                    &request_content.encoded_slice,
+                )
+            })
+            .await?;
+
+        Ok(Response::new(VerifySlicesReply { results }))
+    }
+
+    async fn recover_blob(
+        &self, request: Request<RecoverBlobRequest>,
+    ) -> Result<Response<RecoverBlobReply>, Status> {
+        let remote_addr = request.remote_addr();
+        let request_content = request.into_inner();
+        info!(
+            requester = ?remote_addr,
+            num_rows = request_content.rows.len(),
+            verify = request_content.verify,
+            "Receive recovery task",
+        );
+        metrics::observe_input(
+            "RecoverBlob",
+            request_content.rows.values().map(Vec::len).sum(),
+        );
+
+        let authority = if request_content.verify {
+            Some(decode_authority(
+                &request_content.erasure_commitment,
+                &request_content.storage_root,
+                request_content.commitment_format().into(),
+            )?)
+        } else {
+            None
+        };
+
+        let params = self.params.clone();
+        let (data, dropped_rows) = self
+            .executor
+            .run(move || {
+                let authority = authority.as_ref().map(|(c, r)| (c, r));
+                recover_blob(&params, &request_content.rows, authority)
+            })
+            .await?;
+
+        let data = data.map_err(|error| ServiceError::RecoveryFailed {
+            error,
+            dropped_rows: dropped_rows.len(),
+        })?;
+        Ok(Response::new(RecoverBlobReply { data, dropped_rows }))
     }
 }
 
+pub(crate) fn deserialize_commitment(
+    raw: &[u8], format: CommitmentFormat,
+) -> Result<G1Curve, String> {
+    commitment_from_bytes(raw, format)
+        .map(Into::into)
+        .map_err(|e| e.to_string())
+}
+
+/// Decodes the commitment and the root that slices are verified against.
+fn decode_authority(
+    erasure_commitment: &[u8], storage_root: &[u8], format: CommitmentFormat,
+) -> Result<(G1Curve, [u8; 32]), ServiceError> {
+    let commitment = deserialize_commitment(erasure_commitment, format)
+        .map_err(ServiceError::MalformedCommitment)?;
+    let storage_root = storage_root.try_into().map_err(|_| {
+        ServiceError::MalformedStorageRoot {
+            actual: storage_root.len(),
+        }
+    })?;
+    Ok((commitment, storage_root))
+}
+
+/// Verifies every serialized slice against the commitment and the root.
+#[instrument(skip_all, name = "verify", level = 2)]
+fn verify_slices(
+    params: &ZgSignerParams, commitment: &G1Curve, storage_root: &[u8; 32],
+    encoded_slices: &[Vec<u8>],
+) -> Vec<SliceResult> {
+    let slices: Vec<_> = cfg_iter!(encoded_slices)
+        .map(|raw| EncodedSlice::deserialize_uncompressed(&raw[..]))
+        .collect();
+    let decoded: Vec<_> =
+        slices.iter().map(|slice| slice.as_ref().ok()).collect();
+    let errors = check_slices(params, commitment, storage_root, &decoded);
+
+    slices
+        .into_iter()
+        .zip(errors)
+        .map(|(slice, error)| match (slice, error) {
+            (Err(e), _) => SliceResult {
+                decode_error: e.to_string(),
+                ..Default::default()
+            },
+            (Ok(_), Some(e)) => SliceResult {
+                verifier_error: Some(e.into()),
+                ..Default::default()
+            },
+            (Ok(_), None) => SliceResult {
+                valid: true,
+                ..Default::default()
+            },
+        })
+        .collect()
+}
+
+/// Returns the verification error of each slice, skipping the `None` entries.
+/// Batches share a `DeferredVerifier`; if its batch check fails, the slices
+/// that passed the eager checks are verified again one by one to locate the
+/// bad ones.
+pub(crate) fn check_slices(
+    params: &ZgSignerParams, commitment: &G1Curve, storage_root: &[u8; 32],
+    slices: &[Option<&EncodedSlice>],
+) -> Vec<Option<VerifierError>> {
+    let verify = |slice: &EncodedSlice, deferred_verifier| {
+        slice.verify(params, commitment, storage_root, deferred_verifier)
+    };
+
+    let deferred_verifier =
+        (slices.len() > 1).then(DeferredVerifier::<PE>::new);
+    let errors: Vec<Option<VerifierError>> = cfg_iter!(slices)
+        .map(|slice| verify((*slice)?, deferred_verifier.clone()).err())
+        .collect();
+
+    let Some(deferred_verifier) = deferred_verifier else {
+        return errors;
+    };
+
+    #[cfg(not(feature = "cuda-verifier"))]
+    let passed = deferred_verifier.fast_check();
+    #[cfg(feature = "cuda-verifier")]
+    let passed = deferred_verifier.fast_check_gpu();
+    if passed {
+        return errors;
+    }
+
+    debug!("Deferred check failed, verify slices one by one");
+    cfg_iter!(slices)
+        .zip(errors)
+        .map(|(slice, error)| match (slice, error) {
+            (Some(slice), None) => verify(slice, None).err(),
+            (_, error) => error,
+        })
+        .collect()
+}
+
+/// Recovers the original payload from the rows that decode, sit at their own
+/// index and, when `authority` is given, pass verification. The other rows
+/// are reported as dropped.
+#[instrument(skip_all, name = "recover", level = 2)]
+fn recover_blob(
+    params: &ZgSignerParams, rows: &HashMap<u32, Vec<u8>>,
+    authority: Option<(&G1Curve, &[u8; 32])>,
+) -> (Result<Vec<u8>, String>, Vec<DroppedRow>) {
+    let mut dropped_rows = vec![];
+    let mut drop_row = |index: usize, reason: String| {
+        dropped_rows.push(DroppedRow {
+            index: index as u32,
+            reason,
+            verifier_error: None,
+        })
+    };
+
+    let mut slices = BTreeMap::new();
+    let mut lines: BTreeMap<usize, Vec<Scalar>> = BTreeMap::new();
+    for (&index, raw) in rows {
+        let index = index as usize;
+        let slice = match EncodedSlice::deserialize_uncompressed(&raw[..]) {
+            Ok(slice) => slice,
+            Err(e) => {
+                drop_row(index, format!("Malformed slice: {}", e));
+                continue;
+            }
+        };
+        if index >= BLOB_ROW_ENCODED {
+            drop_row(index, "Row index out of range".to_string());
+            continue;
+        }
+        if slice.index != index {
+            drop_row(
+                index,
+                format!("Slice index {} does not match row", slice.index),
+            );
+            continue;
+        }
+        let line = slice.amt_row();
+        if line.len() != BLOB_COL_N {
+            drop_row(index, "Incorrect row size".to_string());
+            continue;
+        }
+        slices.insert(index, slice);
+        lines.insert(index, line);
+    }
+
+    if let Some((commitment, storage_root)) = authority {
+        let decoded: Vec<_> = slices.values().map(Some).collect();
+        let errors = check_slices(params, commitment, storage_root, &decoded);
+        let failed: Vec<_> = slices
+            .keys()
+            .zip(errors)
+            .filter_map(|(index, error)| Some((*index, error?)))
+            .collect();
+        for (index, error) in failed {
+            lines.remove(&index);
+            dropped_rows.push(DroppedRow {
+                index: index as u32,
+                reason: "Verification failed".to_string(),
+                verifier_error: Some(error.into()),
+            });
+        }
+    }
+
+    if !dropped_rows.is_empty() {
+        info!(dropped = dropped_rows.len(), "Rows dropped before recovery");
+    }
+    dropped_rows.sort_by_key(|row| row.index);
+
+    (recover_from_lines(&lines), dropped_rows)
+}
+
+#[cfg(test)]
+use crate::client::{decode_reply, EncodedReply};
+
 #[cfg(test)]
 impl SignerService {
     pub fn deserialize_reply(
         &self, reply: EncodeBlobReply, encoded_data: &EncodedBlob,
     ) {
         use amt::ec_algebra::CanonicalDeserialize;
-        use ark_bn254::{Fq, G1Affine, G1Projective};
-        use zg_encoder::constants::G1Curve;
         // deserialize
-
-        let erasure_commitment: G1Projective = {
-            let mut raw_commitment = &*reply.erasure_commitment;
-            let x = Fq::deserialize_uncompressed(&mut raw_commitment).unwrap();
-            let y = Fq::deserialize_uncompressed(&mut raw_commitment).unwrap();
-            G1Affine::new(x, y).into()
-        };
-
-        let storage_root =
-            <[u8; 32]>::deserialize_uncompressed(&*reply.storage_root).unwrap();
         let encoded_data_h256: Vec<_> = reply
             .encoded_data
             .chunks_exact(32)
             .map(|x| <[u8; 32]>::deserialize_uncompressed(&*x).unwrap())
             .collect();
-        let encoded_slice: Vec<_> = reply
-            .encoded_slice
-            .iter()
-            .map(|row| {
-                EncodedSlice::deserialize_uncompressed(&*row.to_vec()).unwrap()
-            })
-            .collect();
+        let EncodedReply {
+            erasure_commitment,
+            storage_root,
+            encoded_slice,
+        } = decode_reply(reply).unwrap();
         // test consistency
-        assert_eq!(erasure_commitment, encoded_data.get_commitment());
+        assert_eq!(erasure_commitment, encoded_data.get_affine_commitment());
         assert_eq!(storage_root, encoded_data.get_file_root());
         assert_eq!(encoded_data.get_data().len(), encoded_data_h256.len());
         for index in 0..BLOB_ROW_ENCODED {
@@ -167,14 +983,46 @@ fn serailize_to_bytes<T: CanonicalSerialize>(data: &T) -> Vec<u8> {
 
 #[cfg(test)]
 mod tests {
+    use std::{collections::HashMap, path::PathBuf, time::Instant};
+
     use rand::{rngs::StdRng, Rng, SeedableRng};
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
     use test_case::test_case;
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{
+        transport::{ClientTlsConfig, Endpoint, Identity, Server},
+        Code, Request, Status,
+    };
     use zg_encoder::{
-        constants::{MAX_BLOB_SIZE, MAX_RAW_DATA_SIZE},
-        EncodedBlob, EncoderError, RawBlob, RawData,
+        constants::{
+            Scalar, BLOB_COL_N, BLOB_ROW_ENCODED, BLOB_ROW_N, MAX_BLOB_SIZE,
+            MAX_RAW_DATA_SIZE,
+        },
+        AmtError, CommitmentFormat, EncodedBlob, EncodedSlice, EncoderError,
+        LightEncodedSlice, RawBlob, RawData, RawDataBuilder, VerifierError,
+    };
+
+    use super::{
+        deserialize_commitment,
+        encoder::{
+            encode_blob_rows_reply::Payload, encode_blobs_item,
+            encoder_client::EncoderClient, encoder_server::Encoder,
+            EncodeBlobHeader, EncodeBlobRequest, EncodeResultRequest,
+            EncodedRow, JobState,
+        },
+        recover_blob, stream_rows, verify_slices, ReplyOptions,
     };
+    use base64::Engine;
 
-    use crate::{EncoderService, SignerService};
+    use crate::{
+        auth::Authenticator,
+        cache::{CacheConfig, ReplyCache},
+        cancel::{CancelReason, CancelToken},
+        client, error_info, EncoderServer, EncoderService, Executor,
+        ExecutorConfig, JobTable, ServiceError, SignerService, TlsConfig,
+    };
+    use amt::{ec_algebra::CanonicalDeserialize, EncoderParams};
 
     use once_cell::sync::Lazy;
     const PARAM_DIR: &str = "../crates/amt/pp";
@@ -182,7 +1030,7 @@ mod tests {
         Lazy::new(|| EncoderService::new_for_test(PARAM_DIR));
     static SIGNER_SERVICE: Lazy<SignerService> = Lazy::new(|| {
         Lazy::force(&ENCODER_SERVICE);
-        SignerService::new(PARAM_DIR)
+        SignerService::new(PARAM_DIR, &Default::default()).unwrap()
     });
 
     #[test_case(1 => Ok(()); "one sized data")]
@@ -203,10 +1051,801 @@ mod tests {
             let raw_data: RawData = data[..].try_into().unwrap();
             let raw_blob: RawBlob = raw_data.into();
             let encoded_data =
-                EncodedBlob::build(&raw_blob, &ENCODER_SERVICE.params);
+                EncodedBlob::build(&raw_blob, &ENCODER_SERVICE.params());
             // deserialize
             SIGNER_SERVICE.deserialize_reply(reply, &encoded_data);
         }
         Ok(())
     }
+
+    #[test]
+    fn test_chunked_data() {
+        let mut data = vec![0u8; 12345];
+        StdRng::seed_from_u64(22u64).fill(&mut data[..]);
+
+        let mut builder = RawDataBuilder::new();
+        for chunk in data.chunks(1000) {
+            builder.extend_from_slice(chunk).unwrap();
+        }
+        let chunked_reply = ENCODER_SERVICE
+            .process_raw_data(
+                builder.build(),
+                &ReplyOptions {
+                    require_data: true,
+                    ..Default::default()
+                },
+                &CancelToken::default(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            chunked_reply,
+            ENCODER_SERVICE.process_data(&data, true).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_light_slice() {
+        let mut data = vec![0u8; 1234];
+        StdRng::seed_from_u64(22u64).fill(&mut data[..]);
+        let reply = ENCODER_SERVICE.process_data(&data, true).unwrap();
+        let light_reply = ENCODER_SERVICE
+            .process_data_with_options(
+                &data,
+                &ReplyOptions {
+                    light_slice: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(light_reply.encoded_data, reply.encoded_data);
+
+        let rows = light_reply.encoded_data.chunks_exact(BLOB_COL_N * 32);
+        for ((light_slice, slice), row) in light_reply
+            .encoded_slice
+            .iter()
+            .zip(&reply.encoded_slice)
+            .zip(rows)
+        {
+            let light_slice =
+                LightEncodedSlice::deserialize_uncompressed(&light_slice[..])
+                    .unwrap();
+            let row: Vec<Scalar> = row
+                .chunks_exact(32)
+                .map(|x| Scalar::deserialize_uncompressed(x).unwrap())
+                .collect();
+            let slice =
+                EncodedSlice::deserialize_uncompressed(&slice[..]).unwrap();
+            assert_eq!(light_slice.into_slice(row), slice);
+        }
+    }
+
+    #[test]
+    fn test_row_indices() {
+        let mut data = vec![0u8; 1234];
+        StdRng::seed_from_u64(22u64).fill(&mut data[..]);
+        let reply = ENCODER_SERVICE.process_data(&data, false).unwrap();
+
+        let row_indices = vec![5, 0, (BLOB_ROW_ENCODED - 1) as u32];
+        let subset_reply = ENCODER_SERVICE
+            .process_data_with_options(
+                &data,
+                &ReplyOptions {
+                    row_indices: row_indices.clone(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let expected: Vec<_> = row_indices
+            .iter()
+            .map(|&index| reply.encoded_slice[index as usize].clone())
+            .collect();
+        assert_eq!(subset_reply.encoded_slice, expected);
+        assert_eq!(subset_reply.storage_root, reply.storage_root);
+
+        let error = ENCODER_SERVICE
+            .process_data_with_options(
+                &data,
+                &ReplyOptions {
+                    row_indices: vec![0, BLOB_ROW_ENCODED as u32],
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ServiceError::InvalidRowIndex {
+                index: BLOB_ROW_ENCODED,
+                expected_max: BLOB_ROW_ENCODED,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_cached_reply() {
+        let service = EncoderService::from_params(
+            EncoderParams::from_dir_mont(
+                PARAM_DIR,
+                true,
+                None,
+                &Default::default(),
+            )
+            .unwrap(),
+        )
+        .with_cache(ReplyCache::new(&CacheConfig {
+            max_bytes: usize::MAX,
+        }));
+
+        let mut data = vec![0u8; 1234];
+        StdRng::seed_from_u64(22u64).fill(&mut data[..]);
+        let reply = service.process_data(&data, true).unwrap();
+        assert_eq!(service.process_data(&data, true).unwrap(), reply);
+        service.process_data(&data, false).unwrap();
+
+        let stats = service.cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 2));
+    }
+
+    #[tokio::test]
+    async fn test_submit_encode() {
+        let service = ENCODER_SERVICE.clone();
+        let mut data = vec![0u8; 1234];
+        StdRng::seed_from_u64(22u64).fill(&mut data[..]);
+        let request = EncodeBlobRequest {
+            data: data.clone(),
+            ..Default::default()
+        };
+
+        let job_id = service
+            .submit_encode(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner()
+            .job_id;
+        let job = service
+            .wait_encode_result(Request::new(EncodeResultRequest {
+                job_id: job_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(job.state(), JobState::Done);
+        assert_eq!(
+            job.reply,
+            Some(service.process_data(&data, false).unwrap())
+        );
+
+        let job = service
+            .get_encode_result(Request::new(EncodeResultRequest { job_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(job.state(), JobState::Done);
+
+        let status = service
+            .get_encode_result(Request::new(EncodeResultRequest {
+                job_id: "unknown".into(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+
+        // A full queue rejects the job before it is registered
+        let executor = Executor::new(&ExecutorConfig {
+            max_concurrency: 1,
+            queue_size: 0,
+        });
+        let service = service
+            .with_executor(executor.clone())
+            .with_jobs(JobTable::new(&Default::default()));
+        let _busy = executor.acquire().await.unwrap();
+        let status = service
+            .submit_encode(Request::new(EncodeBlobRequest {
+                data,
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(error_info(&status).unwrap().reason, "QUEUE_FULL");
+        assert_eq!(service.job_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_typed_client() {
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .layer(crate::metrics::GrpcMetricsLayer::default())
+                .add_service(EncoderServer::new(ENCODER_SERVICE.clone()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut data = vec![0u8; 1234];
+        StdRng::seed_from_u64(22u64).fill(&mut data[..]);
+        let mut client =
+            client::EncoderClient::connect(format!("http://{addr}"))
+                .await
+                .unwrap()
+                .with_verifier(SIGNER_SERVICE.params.clone());
+        let reply = client.encode_blob(&data).await.unwrap();
+
+        let raw_data: RawData = data[..].try_into().unwrap();
+        let encoded_blob =
+            EncodedBlob::build(&raw_data.into(), &ENCODER_SERVICE.params());
+        assert_eq!(
+            reply.erasure_commitment,
+            encoded_blob.get_affine_commitment()
+        );
+        assert_eq!(reply.storage_root, encoded_blob.get_file_root());
+        assert_eq!(reply.encoded_slice[7], encoded_blob.get_row(7));
+        let metrics = crate::metrics::render().unwrap();
+        assert!(metrics.contains(
+            "zg_encoder_grpc_requests_total{code=\"Ok\",method=\"EncodeBlob\"}"
+        ));
+
+        // A reply for other data does not verify against this root
+        let mut tampered = reply;
+        tampered.storage_root = [0u8; 32];
+        assert!(matches!(
+            client::verify_reply(&SIGNER_SERVICE.params, &tampered),
+            Err(client::ClientError::InvalidSlice { .. })
+        ));
+    }
+
+    async fn post_json(
+        router: &axum::Router, path: &str, body: serde_json::Value,
+    ) -> (u16, serde_json::Value) {
+        post_json_with_headers(router, path, &[], body).await
+    }
+
+    async fn post_json_with_headers(
+        router: &axum::Router, path: &str, headers: &[(&str, String)],
+        body: serde_json::Value,
+    ) -> (u16, serde_json::Value) {
+        use tower::ServiceExt;
+        let mut request = axum::http::Request::post(path)
+            .header("content-type", "application/json");
+        for (key, value) in headers {
+            request = request.header(*key, value);
+        }
+        let request = request
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status().as_u16();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_http_gateway() {
+        let router = crate::gateway::router(
+            Some(ENCODER_SERVICE.clone()),
+            Some(SIGNER_SERVICE.clone()),
+            Authenticator::new(&Default::default()),
+            crate::MESSAGE_SIZE_LIMIT,
+        );
+        let mut data = vec![0u8; 1234];
+        StdRng::seed_from_u64(22u64).fill(&mut data[..]);
+
+        let (status, reply) = post_json(
+            &router,
+            "/v1/encode",
+            serde_json::json!({ "data": hex::encode(&data), "row_indices": [0, 1] }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        let (status, verified) = post_json(
+            &router,
+            "/v1/verify",
+            serde_json::json!({
+                "erasure_commitment": reply["erasure_commitment"],
+                "storage_root": reply["storage_root"],
+                "encoded_slice": reply["encoded_slice"],
+            }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        let results = verified["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result["valid"] == true));
+
+        let oversized = vec![0u8; MAX_RAW_DATA_SIZE + 1];
+        let (status, error) = post_json(
+            &router,
+            "/v1/encode",
+            serde_json::json!({
+                "data": base64::engine::general_purpose::STANDARD
+                    .encode(&oversized),
+                "encoding": "base64",
+            }),
+        )
+        .await;
+        assert_eq!(status, 400);
+        assert_eq!(error["code"], Code::InvalidArgument as i32);
+        assert_eq!(error["reason"], "TOO_LARGE_BLOB");
+
+        let (status, _) =
+            post_json(&router, "/v1/verify", serde_json::json!({})).await;
+        assert_eq!(status, 400);
+        for (body, reason) in [
+            (
+                serde_json::json!({
+                    "erasure_commitment": "",
+                    "storage_root": reply["storage_root"],
+                    "encoded_slice": [],
+                }),
+                "MALFORMED_COMMITMENT",
+            ),
+            (
+                serde_json::json!({
+                    "erasure_commitment": reply["erasure_commitment"],
+                    "storage_root": "",
+                    "encoded_slice": [],
+                }),
+                "MALFORMED_STORAGE_ROOT",
+            ),
+        ] {
+            let (status, error) = post_json(&router, "/v1/verify", body).await;
+            assert_eq!(status, 400);
+            assert_eq!(error["reason"], reason);
+        }
+        let (status, error) = post_json(
+            &router,
+            "/v1/recover",
+            serde_json::json!({ "rows": {} }),
+        )
+        .await;
+        assert_eq!(status, 400);
+        assert_eq!(error["reason"], "RECOVERY_FAILED");
+        assert_eq!(error["metadata"]["dropped_rows"], "0");
+
+        let router = crate::gateway::router(
+            Some(ENCODER_SERVICE.clone()),
+            Some(SIGNER_SERVICE.clone()),
+            Authenticator::new(&crate::AuthConfig {
+                keys: HashMap::from([("client".into(), "secret".into())]),
+            }),
+            crate::MESSAGE_SIZE_LIMIT,
+        );
+        for path in ["/v1/encode", "/v1/verify", "/v1/recover"] {
+            let (status, error) =
+                post_json(&router, path, serde_json::json!({})).await;
+            assert_eq!(status, 401);
+            assert_eq!(error["reason"], "UNAUTHENTICATED");
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let signed = |path: &str| {
+            [
+                (crate::CLIENT_ID_HEADER, "client".to_string()),
+                (crate::TIMESTAMP_HEADER, now.to_string()),
+                (
+                    crate::SIGNATURE_HEADER,
+                    crate::sign_request("secret", "client", now, path),
+                ),
+            ]
+        };
+        // Past authentication, the empty body is rejected
+        let (status, _) = post_json_with_headers(
+            &router,
+            "/v1/verify",
+            &signed("/v1/verify"),
+            serde_json::json!({}),
+        )
+        .await;
+        assert_eq!(status, 400);
+        let (status, _) = post_json_with_headers(
+            &router,
+            "/v1/verify",
+            &signed("/v1/recover"),
+            serde_json::json!({}),
+        )
+        .await;
+        assert_eq!(status, 401);
+    }
+
+    #[test]
+    fn test_commitment_format() {
+        let mut data = vec![0u8; 1234];
+        StdRng::seed_from_u64(22u64).fill(&mut data[..]);
+        let reply = ENCODER_SERVICE.process_data(&data, false).unwrap();
+        let commitment = deserialize_commitment(
+            &reply.erasure_commitment,
+            CommitmentFormat::ArkUncompressed,
+        )
+        .unwrap();
+
+        for format in [
+            CommitmentFormat::ArkCompressed,
+            CommitmentFormat::EvmBigEndian,
+        ] {
+            let formatted = ENCODER_SERVICE
+                .process_data_with_options(
+                    &data,
+                    &ReplyOptions {
+                        commitment_format: format,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            assert_eq!(formatted.erasure_commitment.len(), format.size());
+            assert_eq!(formatted.encoded_slice, reply.encoded_slice);
+            assert_eq!(
+                deserialize_commitment(&formatted.erasure_commitment, format),
+                Ok(commitment)
+            );
+        }
+    }
+
+    #[test]
+    fn test_batch() {
+        let mut rng = StdRng::seed_from_u64(22u64);
+        let requests: Vec<_> = [1234, MAX_RAW_DATA_SIZE + 1, 12345]
+            .into_iter()
+            .map(|num_bytes| {
+                let mut data = vec![0u8; num_bytes];
+                rng.fill(&mut data[..]);
+                EncodeBlobRequest {
+                    data,
+                    require_data: true,
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        let replies = ENCODER_SERVICE
+            .process_batch(requests.clone(), &CancelToken::default());
+        assert_eq!(replies.len(), requests.len());
+        for (request, item) in requests.iter().zip(replies.iter()) {
+            let expected =
+                match ENCODER_SERVICE.process_data(&request.data, true) {
+                    Ok(reply) => encode_blobs_item::Result::Reply(reply),
+                    Err(e) => encode_blobs_item::Result::Error(
+                        ServiceError::from(e).into(),
+                    ),
+                };
+            assert_eq!(item.result, Some(expected));
+        }
+
+        let Some(encode_blobs_item::Result::Error(error)) = &replies[1].result
+        else {
+            panic!("Oversized blob was encoded");
+        };
+        assert_eq!(error.code, Code::InvalidArgument as i32);
+        assert_eq!(error.reason, "TOO_LARGE_BLOB");
+        assert_eq!(
+            error.metadata["expected_max"],
+            MAX_RAW_DATA_SIZE.to_string()
+        );
+    }
+
+    #[test]
+    fn test_stream_rows() {
+        let mut data = vec![0u8; 1234];
+        StdRng::seed_from_u64(22u64).fill(&mut data[..]);
+
+        let stream = |options: &ReplyOptions| {
+            let (tx, rx) = mpsc::channel(BLOB_ROW_ENCODED + 1);
+            stream_rows(
+                &ENCODER_SERVICE.params(),
+                data[..].try_into().unwrap(),
+                options,
+                tx,
+                &CancelToken::default(),
+            );
+            rx
+        };
+        let mut rx = stream(&ReplyOptions::default());
+        let reply = ENCODER_SERVICE.process_data(&data, false).unwrap();
+
+        let header = rx.blocking_recv().unwrap().unwrap().payload;
+        assert_eq!(
+            header,
+            Some(Payload::Header(EncodeBlobHeader {
+                version: reply.version,
+                erasure_commitment: reply.erasure_commitment,
+                storage_root: reply.storage_root,
+            }))
+        );
+        for (index, encoded_slice) in
+            reply.encoded_slice.iter().cloned().enumerate()
+        {
+            let row = rx.blocking_recv().unwrap().unwrap().payload;
+            assert_eq!(
+                row,
+                Some(Payload::Row(EncodedRow {
+                    index: index as u32,
+                    encoded_slice,
+                }))
+            );
+        }
+        assert!(rx.blocking_recv().is_none());
+
+        let row_indices = vec![5, 0, (BLOB_ROW_ENCODED - 1) as u32];
+        let mut rx = stream(&ReplyOptions {
+            row_indices: row_indices.clone(),
+            ..Default::default()
+        });
+        assert!(matches!(
+            rx.blocking_recv().unwrap().unwrap().payload,
+            Some(Payload::Header(_))
+        ));
+        for index in row_indices {
+            let row = rx.blocking_recv().unwrap().unwrap().payload;
+            assert_eq!(
+                row,
+                Some(Payload::Row(EncodedRow {
+                    index,
+                    encoded_slice: reply.encoded_slice[index as usize].clone(),
+                }))
+            );
+        }
+        assert!(rx.blocking_recv().is_none());
+    }
+
+    #[test]
+    fn test_rows_stream_options() {
+        let options = |require_data, light_slice, row_indices| ReplyOptions {
+            require_data,
+            light_slice,
+            row_indices,
+            ..Default::default()
+        };
+        assert!(options(false, false, vec![0, 1])
+            .validate_rows_stream()
+            .is_ok());
+        for (options, reason) in [
+            (options(true, false, vec![]), "UNSUPPORTED_OPTION"),
+            (options(false, true, vec![]), "UNSUPPORTED_OPTION"),
+            (
+                options(false, false, vec![BLOB_ROW_ENCODED as u32]),
+                "INVALID_ROW_INDEX",
+            ),
+        ] {
+            let status =
+                Status::from(options.validate_rows_stream().unwrap_err());
+            assert_eq!(status.code(), Code::InvalidArgument);
+            assert_eq!(error_info(&status).unwrap().reason, reason);
+        }
+    }
+
+    #[test]
+    fn test_cancelled_encode() {
+        let data = vec![7u8; 1234];
+        let options = ReplyOptions::default();
+        let encode = |cancel: &CancelToken| {
+            ENCODER_SERVICE.process_raw_data(
+                data[..].try_into().unwrap(),
+                &options,
+                cancel,
+            )
+        };
+
+        let cancel = CancelToken::default();
+        cancel.cancel(CancelReason::Disconnected);
+        assert!(matches!(
+            encode(&cancel),
+            Err(ServiceError::Cancelled(CancelReason::Disconnected))
+        ));
+        let expired = CancelToken::default().with_deadline(Instant::now());
+        let status = Status::from(encode(&expired).unwrap_err());
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+
+        let replies = ENCODER_SERVICE.process_batch(
+            vec![EncodeBlobRequest {
+                data: data.clone(),
+                ..Default::default()
+            }],
+            &cancel,
+        );
+        assert!(matches!(
+            replies[0].result,
+            Some(encode_blobs_item::Result::Error(_))
+        ));
+
+        let (tx, mut rx) = mpsc::channel(1);
+        stream_rows(
+            &ENCODER_SERVICE.params(),
+            data[..].try_into().unwrap(),
+            &options,
+            tx,
+            &expired,
+        );
+        let status = rx.blocking_recv().unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::DeadlineExceeded);
+        assert!(rx.blocking_recv().is_none());
+        assert!(encode(&CancelToken::default()).is_ok());
+    }
+
+    #[test]
+    fn test_verify_slices() {
+        let mut rng = StdRng::seed_from_u64(22u64);
+        let mut data = vec![0u8; 1234];
+        rng.fill(&mut data[..]);
+        let reply = ENCODER_SERVICE.process_data(&data, false).unwrap();
+        rng.fill(&mut data[..]);
+        let other_reply = ENCODER_SERVICE.process_data(&data, false).unwrap();
+
+        let commitment = deserialize_commitment(
+            &reply.erasure_commitment,
+            CommitmentFormat::ArkUncompressed,
+        )
+        .unwrap();
+        let storage_root: [u8; 32] = reply.storage_root[..].try_into().unwrap();
+        let mut encoded_slices = reply.encoded_slice;
+
+        let results = verify_slices(
+            &SIGNER_SERVICE.params,
+            &commitment,
+            &storage_root,
+            &encoded_slices,
+        );
+        assert!(results.iter().all(|result| result.valid));
+
+        encoded_slices[1].clone_from(&other_reply.encoded_slice[1]);
+        encoded_slices[2] = vec![0u8; 10];
+        let results = verify_slices(
+            &SIGNER_SERVICE.params,
+            &commitment,
+            &storage_root,
+            &encoded_slices,
+        );
+        assert_eq!(
+            results[1].verifier_error,
+            Some(VerifierError::AMT(AmtError::IncorrectCommitment).into())
+        );
+        assert!(!results[2].decode_error.is_empty());
+        for (index, result) in results.iter().enumerate() {
+            assert_eq!(result.valid, index != 1 && index != 2);
+        }
+    }
+
+    #[test]
+    fn test_recover_blob() {
+        let mut rng = StdRng::seed_from_u64(22u64);
+        let mut data = vec![0u8; 12345];
+        rng.fill(&mut data[..]);
+        let other_reply = ENCODER_SERVICE.process_data(&data, false).unwrap();
+        rng.fill(&mut data[..]);
+        let reply = ENCODER_SERVICE.process_data(&data, false).unwrap();
+
+        let commitment = deserialize_commitment(
+            &reply.erasure_commitment,
+            CommitmentFormat::ArkUncompressed,
+        )
+        .unwrap();
+        let storage_root: [u8; 32] = reply.storage_root[..].try_into().unwrap();
+
+        let mut rows: HashMap<u32, Vec<u8>> = reply
+            .encoded_slice
+            .into_iter()
+            .enumerate()
+            .skip(BLOB_ROW_ENCODED - BLOB_ROW_N - 1)
+            .map(|(index, row)| (index as u32, row))
+            .collect();
+        let first = (BLOB_ROW_ENCODED - BLOB_ROW_N - 1) as u32;
+        rows.insert(first, other_reply.encoded_slice[first as usize].clone());
+
+        let (recovered, dropped_rows) = recover_blob(
+            &SIGNER_SERVICE.params,
+            &rows,
+            Some((&commitment, &storage_root)),
+        );
+        assert_eq!(recovered.unwrap(), data);
+        assert_eq!(dropped_rows.len(), 1);
+        assert_eq!(dropped_rows[0].index, first);
+        assert!(dropped_rows[0].verifier_error.is_some());
+
+        rows.insert(first + 1, vec![0u8; 10]);
+        let (recovered, dropped_rows) = recover_blob(
+            &SIGNER_SERVICE.params,
+            &rows,
+            Some((&commitment, &storage_root)),
+        );
+        assert!(recovered.is_err());
+        assert_eq!(dropped_rows.len(), 2);
+    }
+
+    fn write_pem(dir: &std::path::Path, name: &str, pem: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, pem).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls() {
+        let mut ca_params = CertificateParams::new(vec![]);
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = Certificate::from_params(ca_params).unwrap();
+        let server_cert =
+            Certificate::from_params(CertificateParams::new(vec![
+                "localhost".to_string()
+            ]))
+            .unwrap();
+        let client_cert =
+            Certificate::from_params(CertificateParams::new(vec![])).unwrap();
+
+        let dir = std::env::temp_dir()
+            .join(format!("zg-encoder-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ca_pem = ca.serialize_pem().unwrap();
+        let tls = TlsConfig {
+            cert_path: write_pem(
+                &dir,
+                "server.crt",
+                &server_cert.serialize_pem_with_signer(&ca).unwrap(),
+            ),
+            key_path: write_pem(
+                &dir,
+                "server.key",
+                &server_cert.serialize_private_key_pem(),
+            ),
+            client_ca_path: Some(write_pem(&dir, "ca.crt", &ca_pem)),
+        };
+
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Server::builder()
+            .tls_config(tls.server_config().unwrap())
+            .unwrap()
+            .add_service(EncoderServer::new(ENCODER_SERVICE.clone()))
+            .serve_with_incoming(TcpListenerStream::new(listener));
+        tokio::spawn(server);
+
+        let client_tls = ClientTlsConfig::new()
+            .ca_certificate(tonic::transport::Certificate::from_pem(&ca_pem))
+            .domain_name("localhost");
+        let request = || EncodeBlobRequest {
+            data: vec![0u8; MAX_RAW_DATA_SIZE + 1],
+            ..Default::default()
+        };
+
+        // Reaches the service, which rejects the oversized blob
+        let channel = Endpoint::from_shared(format!("https://{addr}"))
+            .unwrap()
+            .tls_config(client_tls.clone().identity(Identity::from_pem(
+                client_cert.serialize_pem_with_signer(&ca).unwrap(),
+                client_cert.serialize_private_key_pem(),
+            )))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let status = EncoderClient::new(channel)
+            .encode_blob(request())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        // Without a client certificate the handshake is refused. With TLS 1.3
+        // the client may only learn it on its first call
+        let connected = Endpoint::from_shared(format!("https://{addr}"))
+            .unwrap()
+            .tls_config(client_tls)
+            .unwrap()
+            .connect()
+            .await;
+        if let Ok(channel) = connected {
+            let status = EncoderClient::new(channel)
+                .encode_blob(request())
+                .await
+                .unwrap_err();
+            // The call fails in the transport, it never reaches the service
+            assert!(
+                std::error::Error::source(&status).is_some_and(
+                    |source| source.is::<tonic::transport::Error>()
+                ),
+                "{status:?}"
+            );
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
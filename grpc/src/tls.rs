@@ -0,0 +1,38 @@
+use std::{fs, io, path::PathBuf};
+
+use sha2::{Digest, Sha256};
+use tonic::{
+    transport::{Certificate, Identity, ServerTlsConfig},
+    Request,
+};
+
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// PEM certificate chain presented by the server.
+    pub cert_path: PathBuf,
+    /// PEM private key of the server certificate.
+    pub key_path: PathBuf,
+    /// PEM bundle of CAs trusted for client certificates. When set, clients
+    /// must present a certificate signed by one of them (mutual TLS).
+    pub client_ca_path: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    pub fn server_config(&self) -> io::Result<ServerTlsConfig> {
+        let cert = fs::read(&self.cert_path)?;
+        let key = fs::read(&self.key_path)?;
+        let mut config =
+            ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+        if let Some(client_ca_path) = &self.client_ca_path {
+            let client_ca = fs::read(client_ca_path)?;
+            config = config.client_ca_root(Certificate::from_pem(client_ca));
+        }
+        Ok(config)
+    }
+}
+
+/// SHA-256 fingerprint of the client certificate, if the peer presented one.
+pub fn peer_identity<T>(request: &Request<T>) -> Option<String> {
+    let certs = request.peer_certs()?;
+    Some(hex::encode(Sha256::digest(certs.first()?.get_ref())))
+}
@@ -1,63 +1,83 @@
 #[macro_use]
 extern crate tracing;
 
-use std::{error::Error, net::SocketAddr, str::FromStr};
+use std::error::Error;
 
-use anyhow::{anyhow, bail, Result};
-use config::Config;
-use tracing::Level;
+use server_config::{ServerConfig, DEFAULT_CONFIG};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing_subscriber::{
+    filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt, Layer,
+};
+
+mod server_config;
 
 mod cli {
     use clap::{arg, command, Command};
 
     pub fn cli_app<'a>() -> Command<'a> {
         command!()
-            .arg(arg!(-c --config <FILE> "Sets a custom config file"))
+            .arg(arg!(-c --config [FILE] "Sets a custom config file"))
+            .arg(arg!(
+                --"print-default-config"
+                "Prints a commented config with the default values and exits"
+            ))
             .allow_external_subcommands(true)
     }
 }
 
-struct ServerConfig {
-    settings: Config,
+fn main() -> Result<(), Box<dyn Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let result = runtime.block_on(run());
+    // don't wait for encodes still running after the drain timeout
+    runtime.shutdown_background();
+    result
 }
 
-impl ServerConfig {
-    pub fn new(matches: clap::ArgMatches) -> Result<Self> {
-        if let Some(config_file) = matches.value_of("config") {
-            let settings = Config::builder()
-                .add_source(config::File::with_name(config_file))
-                .build()?;
-            Ok(Self { settings })
-        } else {
-            bail!(anyhow!("Config file missing!"));
-        }
-    }
-
-    pub fn get_string(&self, s: &str) -> Result<String> {
-        Ok(self.settings.get_string(s)?)
+async fn run() -> Result<(), Box<dyn Error>> {
+    // CLI, config
+    let matches = cli::cli_app().get_matches();
+    if matches.is_present("print-default-config") {
+        print!("{DEFAULT_CONFIG}");
+        return Ok(());
     }
-}
+    let config_file =
+        matches.value_of("config").ok_or("Config file missing!")?;
+    let settings = ServerConfig::load(config_file)?.validate()?;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    // enable backtraces
+    // enable backtraces, after config errors are reported without them
     std::env::set_var("RUST_BACKTRACE", "1");
 
-    // CLI, config
-    let matches = cli::cli_app().get_matches();
-    let server_config = ServerConfig::new(matches)?;
-    let log_level =
-        Level::from_str(&server_config.get_string("log_level")?).unwrap();
-    let params_dir = server_config.get_string("params_dir")?;
-
-    // tracing
-    tracing_subscriber::fmt().with_max_level(log_level).init();
+    // tracing, and stage timings when metrics are served
+    let stage_timings = settings
+        .options
+        .metrics_listen_address
+        .map(|_| grpc::metrics::stage_layer());
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_filter(LevelFilter::from_level(settings.log_level)),
+        )
+        .with(stage_timings)
+        .init();
 
     // start server
-    let server_addr = server_config.get_string("grpc_listen_address")?;
+    let server_addr = settings.grpc_listen_address;
+    info!(%server_addr, "Starting grpc server");
 
-    info!(server_addr, "Starting grpc server");
+    grpc::run_server_with_shutdown(
+        server_addr,
+        &settings.params_dir,
+        settings.options,
+        shutdown_signal(),
+    )
+    .await
+}
 
-    grpc::run_server(SocketAddr::from_str(&server_addr).unwrap(), &params_dir)
-        .await
+async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate())
+        .expect("Failed to install SIGTERM handler");
+    tokio::select! {
+        _ = terminate.recv() => info!("Received SIGTERM"),
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT"),
+    }
 }
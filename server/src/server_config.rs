@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap, net::SocketAddr, path::PathBuf, str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use config::{Config, Environment, File};
+use grpc::{
+    AuthConfig, CacheConfig, ExecutorConfig, JobConfig, ParamsCheck,
+    ServerOptions, TlsConfig,
+};
+use serde::Deserialize;
+use tracing::Level;
+
+/// Commented config with every key at its default value, printed by
+/// `--print-default-config`.
+pub const DEFAULT_CONFIG: &str = include_str!("../../run/config.toml");
+
+/// Prefix of the environment variables overriding config keys, e.g.
+/// `ZG_ENCODER_PARAMS_DIR`.
+const ENV_PREFIX: &str = "ZG_ENCODER";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub log_level: String,
+    pub params_dir: String,
+    pub params_check: String,
+    pub allow_params_reload: bool,
+    pub grpc_listen_address: SocketAddr,
+    pub http_listen_address: Option<SocketAddr>,
+    pub metrics_listen_address: Option<SocketAddr>,
+    pub enable_encoder_service: bool,
+    pub enable_signer_service: bool,
+    pub max_message_bytes: usize,
+    pub max_concurrent_encodes: usize,
+    pub max_queued_encodes: usize,
+    pub reply_cache_bytes: usize,
+    pub job_result_ttl_secs: u64,
+    pub max_encode_jobs: usize,
+    pub drain_timeout_secs: u64,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub tls_client_ca: Option<PathBuf>,
+    pub auth_keys: HashMap<String, String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        let options = ServerOptions::default();
+        Self {
+            log_level: "info".into(),
+            params_dir: "./params".into(),
+            params_check: "enforce".into(),
+            allow_params_reload: options.allow_params_reload,
+            grpc_listen_address: ([0, 0, 0, 0], 34000).into(),
+            http_listen_address: options.http_listen_address,
+            metrics_listen_address: options.metrics_listen_address,
+            enable_encoder_service: options.enable_encoder,
+            enable_signer_service: options.enable_signer,
+            max_message_bytes: options.max_message_size,
+            max_concurrent_encodes: options.executor.max_concurrency,
+            max_queued_encodes: options.executor.queue_size,
+            reply_cache_bytes: options.cache.max_bytes,
+            job_result_ttl_secs: options.jobs.result_ttl.as_secs(),
+            max_encode_jobs: options.jobs.max_jobs,
+            drain_timeout_secs: options.drain_timeout.as_secs(),
+            tls_cert: None,
+            tls_key: None,
+            tls_client_ca: None,
+            auth_keys: options.auth.keys,
+        }
+    }
+}
+
+/// Checked config, ready to start the server with.
+pub struct Settings {
+    pub log_level: Level,
+    pub params_dir: String,
+    pub grpc_listen_address: SocketAddr,
+    pub options: ServerOptions,
+}
+
+impl ServerConfig {
+    /// Reads the config file, then applies the `ZG_ENCODER_*` environment
+    /// variables over it.
+    pub fn load(path: &str) -> Result<Self> {
+        Self::from_sources(path, Environment::with_prefix(ENV_PREFIX))
+    }
+
+    fn from_sources(path: &str, env: Environment) -> Result<Self> {
+        Config::builder()
+            .add_source(File::with_name(path))
+            .add_source(env)
+            .build()
+            .and_then(Config::try_deserialize)
+            .map_err(|e| anyhow!("Invalid config {path}: {e}"))
+    }
+
+    pub fn validate(self) -> Result<Settings> {
+        let log_level = Level::from_str(&self.log_level).map_err(|_| {
+            anyhow!(
+                "log_level: expected one of trace, debug, info, warn or \
+                 error, got {:?}",
+                self.log_level
+            )
+        })?;
+        let params_check = match self.params_check.as_str() {
+            "enforce" => ParamsCheck::Enforce,
+            "warn" => ParamsCheck::Warn,
+            other => {
+                bail!("params_check: expected enforce or warn, got {:?}", other)
+            }
+        };
+        if !self.enable_encoder_service && !self.enable_signer_service {
+            bail!(
+                "enable_encoder_service, enable_signer_service: at least one \
+                 service must be enabled"
+            );
+        }
+        if !PathBuf::from(&self.params_dir).is_dir() {
+            bail!("params_dir: {:?} is not a directory", self.params_dir);
+        }
+        if self.http_listen_address == Some(self.grpc_listen_address) {
+            bail!("http_listen_address: same as grpc_listen_address");
+        }
+        if let Some(address) = self.metrics_listen_address {
+            if address == self.grpc_listen_address
+                || Some(address) == self.http_listen_address
+            {
+                bail!("metrics_listen_address: already used by another server");
+            }
+        }
+        for (key, value) in [
+            ("max_message_bytes", self.max_message_bytes),
+            ("max_concurrent_encodes", self.max_concurrent_encodes),
+        ] {
+            if value == 0 {
+                bail!("{key}: must be at least 1");
+            }
+        }
+
+        let tls = match (self.tls_cert, self.tls_key) {
+            (Some(cert_path), Some(key_path)) => Some(TlsConfig {
+                cert_path,
+                key_path,
+                client_ca_path: self.tls_client_ca,
+            }),
+            (None, None) if self.tls_client_ca.is_some() => {
+                bail!("tls_client_ca: requires tls_cert and tls_key")
+            }
+            (None, None) => None,
+            _ => bail!("tls_cert, tls_key: must be set together"),
+        };
+        if let Some(tls) = &tls {
+            for (key, path) in [
+                ("tls_cert", Some(&tls.cert_path)),
+                ("tls_key", Some(&tls.key_path)),
+                ("tls_client_ca", tls.client_ca_path.as_ref()),
+            ] {
+                if let Some(path) = path.filter(|path| !path.is_file()) {
+                    bail!("{key}: {} is not a file", path.display());
+                }
+            }
+        }
+        if let Some((client, _)) =
+            self.auth_keys.iter().find(|(_, secret)| secret.is_empty())
+        {
+            bail!("auth_keys: empty secret for client {client:?}");
+        }
+
+        Ok(Settings {
+            log_level,
+            params_dir: self.params_dir,
+            grpc_listen_address: self.grpc_listen_address,
+            options: ServerOptions {
+                executor: ExecutorConfig {
+                    max_concurrency: self.max_concurrent_encodes,
+                    queue_size: self.max_queued_encodes,
+                },
+                cache: CacheConfig {
+                    max_bytes: self.reply_cache_bytes,
+                },
+                jobs: JobConfig {
+                    result_ttl: Duration::from_secs(self.job_result_ttl_secs),
+                    max_jobs: self.max_encode_jobs,
+                },
+                auth: AuthConfig {
+                    keys: self.auth_keys,
+                },
+                tls,
+                http_listen_address: self.http_listen_address,
+                metrics_listen_address: self.metrics_listen_address,
+                drain_timeout: Duration::from_secs(self.drain_timeout_secs),
+                max_message_size: self.max_message_bytes,
+                enable_encoder: self.enable_encoder_service,
+                enable_signer: self.enable_signer_service,
+                params_check,
+                allow_params_reload: self.allow_params_reload,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Config, Environment, File, FileFormat};
+
+    use super::{ServerConfig, DEFAULT_CONFIG};
+
+    #[test]
+    fn test_default_config() {
+        let config: ServerConfig = Config::builder()
+            .add_source(File::from_str(DEFAULT_CONFIG, FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(config, ServerConfig::default());
+    }
+
+    #[test]
+    fn test_env_override() {
+        let env = Environment::with_prefix("ZG_ENCODER").source(Some(
+            [
+                ("ZG_ENCODER_PARAMS_DIR", "../crates/amt/pp"),
+                ("ZG_ENCODER_MAX_CONCURRENT_ENCODES", "4"),
+                ("ZG_ENCODER_ENABLE_SIGNER_SERVICE", "false"),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect(),
+        ));
+        let config =
+            ServerConfig::from_sources("../run/config.toml", env).unwrap();
+        assert_eq!(config.params_dir, "../crates/amt/pp");
+        assert_eq!(config.max_concurrent_encodes, 4);
+
+        let settings = config.clone().validate().unwrap();
+        assert_eq!(settings.options.executor.max_concurrency, 4);
+        assert!(!settings.options.enable_signer);
+
+        let invalid = ServerConfig {
+            log_level: "verbose".into(),
+            ..config.clone()
+        };
+        let error = invalid.validate().err().unwrap().to_string();
+        assert!(error.starts_with("log_level"), "{error}");
+
+        let invalid = ServerConfig {
+            tls_cert: Some("server.crt".into()),
+            ..config
+        };
+        let error = invalid.validate().err().unwrap().to_string();
+        assert!(error.starts_with("tls_cert, tls_key"), "{error}");
+    }
+}